@@ -0,0 +1,79 @@
+use super::rectangle;
+
+// Footprint on the ground plane, and the range of heights above the ground it fills
+#[derive(Clone, PartialEq, Debug)]
+pub struct Aabb {
+    footprint: rectangle::Rectangle,
+    y_range: (f32, f32),
+}
+
+impl Aabb {
+    pub fn new(footprint: rectangle::Rectangle, y_range: (f32, f32)) -> Aabb {
+        Aabb { footprint, y_range }
+    }
+
+    pub fn get_footprint(&self) -> &rectangle::Rectangle {
+        &self.footprint
+    }
+
+    fn y_center(&self) -> f32 {
+        0.50 * (self.y_range.0 + self.y_range.1)
+    }
+
+    fn height(&self) -> f32 {
+        self.y_range.1 - self.y_range.0
+    }
+
+    // Fraction of the way from `previous` to `self` where it first touches `other`
+    pub fn sweep(&self, previous: &Aabb, other: &Aabb) -> Option<f32> {
+        let vertical = rectangle::Rectangle::sweep_axis(
+            previous.y_center(),
+            self.y_center(),
+            other.y_center(),
+            0.50 * (self.height() + other.height()),
+        )?;
+        self.footprint
+            .sweep_within(&previous.footprint, &other.footprint, vertical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: f32) -> Aabb {
+        Aabb::new(
+            rectangle::Rectangle::new((0.0, 20.0), (100.0, 10.0)),
+            (0.0, height),
+        )
+    }
+
+    fn flying(z: f32, y: f32) -> Aabb {
+        Aabb::new(
+            rectangle::Rectangle::new((0.0, z), (10.0, 10.0)),
+            (y - 5.0, y + 5.0),
+        )
+    }
+
+    #[test]
+    fn flying_over_low_block_misses() {
+        assert! {flying(40.0, 120.0).sweep(&flying(0.0, 120.0), &block(100.0)).is_none()}
+        assert! {flying(40.0, 120.0).sweep(&flying(0.0, 120.0), &block(150.0)).is_some()}
+    }
+
+    #[test]
+    fn descending_onto_block_hits_when_below_its_top() {
+        let previous = flying(0.0, 140.0);
+        let current = flying(40.0, 60.0);
+        let toi = current.sweep(&previous, &block(100.0)).unwrap();
+        // The footprints meet at 10 / 40, the ship is still above the block until 35 / 80
+        assert! {(toi - 35.0 / 80.0).abs() < 1e-6}
+    }
+
+    #[test]
+    fn climbing_clear_before_reaching_block_misses() {
+        let previous = flying(0.0, 20.0);
+        let current = flying(40.0, 220.0);
+        assert! {current.sweep(&previous, &block(50.0)).is_none()}
+    }
+}
@@ -0,0 +1,311 @@
+use super::engine;
+use super::storage;
+use engine::KeyCode;
+
+const STORAGE_KEY: &str = "bindings";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Action {
+    Accelerate,
+    Decelerate,
+    Left,
+    Right,
+    Pause,
+    Restart,
+    Confirm,
+    Hop,
+    Camera,
+}
+
+pub const ACTIONS: [Action; 9] = [
+    Action::Accelerate,
+    Action::Decelerate,
+    Action::Left,
+    Action::Right,
+    Action::Pause,
+    Action::Restart,
+    Action::Confirm,
+    Action::Hop,
+    Action::Camera,
+];
+
+// Keys that can be bound, anything else is ignored when remapping
+const BINDABLE_KEYS: [KeyCode; 58] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Space,
+    KeyCode::Enter,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Backspace,
+    KeyCode::LeftShift,
+    KeyCode::RightShift,
+    KeyCode::LeftControl,
+    KeyCode::RightControl,
+    KeyCode::LeftAlt,
+    KeyCode::RightAlt,
+    KeyCode::Kp0,
+    KeyCode::Kp2,
+    KeyCode::Kp4,
+    KeyCode::Kp5,
+    KeyCode::Kp6,
+    KeyCode::Kp8,
+    KeyCode::KpEnter,
+];
+
+impl Action {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Action::Accelerate => "accelerate",
+            Action::Decelerate => "decelerate",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Pause => "pause",
+            Action::Restart => "restart",
+            Action::Confirm => "confirm",
+            Action::Hop => "hop",
+            Action::Camera => "camera",
+        }
+    }
+
+    fn index(&self) -> usize {
+        ACTIONS.iter().position(|action| action == self).unwrap()
+    }
+}
+
+pub fn is_bindable(key: KeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .copied()
+        .find(|key| key_name(*key) == name)
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bindings {
+    keys: [Vec<KeyCode>; ACTIONS.len()],
+}
+
+impl Bindings {
+    pub fn new() -> Bindings {
+        Bindings {
+            keys: [
+                vec![KeyCode::W, KeyCode::Up],
+                vec![KeyCode::S, KeyCode::Down],
+                vec![KeyCode::A, KeyCode::Left],
+                vec![KeyCode::D, KeyCode::Right],
+                vec![KeyCode::P, KeyCode::Escape],
+                vec![KeyCode::R],
+                vec![KeyCode::Enter],
+                vec![KeyCode::Space],
+                vec![KeyCode::C],
+            ],
+        }
+    }
+
+    pub fn load() -> Bindings {
+        match storage::load(STORAGE_KEY) {
+            Some(text) => Bindings::from_text(&text),
+            None => Bindings::new(),
+        }
+    }
+
+    pub fn save(&self) {
+        storage::save(STORAGE_KEY, &self.to_text());
+    }
+
+    pub fn get_keys(&self, action: Action) -> &[KeyCode] {
+        &self.keys[action.index()]
+    }
+
+    // "W|Up", for instructions drawn on screen
+    pub fn get_label(&self, action: Action) -> String {
+        let names: Vec<String> = self
+            .get_keys(action)
+            .iter()
+            .map(|key| key_name(*key))
+            .collect();
+        names.join("|")
+    }
+
+    // First bound key, where there is no room for all of them
+    pub fn get_primary_label(&self, action: Action) -> String {
+        key_name(self.get_keys(action)[0])
+    }
+
+    // Adds the key to the action, or removes it if it was bound already.
+    // A key moves away from any other action, and no action is left without keys.
+    pub fn toggle(&mut self, action: Action, key: KeyCode) {
+        let keys = &self.keys[action.index()];
+        if keys.contains(&key) {
+            if keys.len() > 1 {
+                self.keys[action.index()].retain(|bound| *bound != key);
+            }
+            return;
+        }
+        let owner = ACTIONS
+            .iter()
+            .find(|other| self.get_keys(**other).contains(&key));
+        if let Some(owner) = owner {
+            if self.get_keys(*owner).len() == 1 {
+                return;
+            }
+            self.keys[owner.index()].retain(|bound| *bound != key);
+        }
+        self.keys[action.index()].push(key);
+    }
+
+    pub fn active_keys(&self, is_key_down: impl Fn(KeyCode) -> bool) -> engine::MoveKeys {
+        let is_down = |action: Action| self.get_keys(action).iter().any(|key| is_key_down(*key));
+        engine::MoveKeys {
+            up: is_down(Action::Accelerate),
+            down: is_down(Action::Decelerate),
+            left: is_down(Action::Left),
+            right: is_down(Action::Right),
+            pause: is_down(Action::Pause),
+            restart: is_down(Action::Restart),
+            confirm: is_down(Action::Confirm),
+            hop: is_down(Action::Hop),
+            camera: is_down(Action::Camera),
+            ..engine::MoveKeys::NONE
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for action in ACTIONS {
+            let names: Vec<String> = self
+                .get_keys(action)
+                .iter()
+                .map(|key| key_name(*key))
+                .collect();
+            text += &format!("{} = {}\n", action.get_name(), names.join(", "));
+        }
+        text
+    }
+
+    // Unknown lines or keys are skipped, actions left without keys keep their defaults
+    fn from_text(text: &str) -> Bindings {
+        let mut bindings = Bindings::new();
+        for line in text.lines() {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let Some(action) = ACTIONS
+                .iter()
+                .find(|action| action.get_name() == name.trim())
+            else {
+                continue;
+            };
+            let keys: Vec<KeyCode> = value
+                .split(',')
+                .filter_map(|key| parse_key(key.trim()))
+                .collect();
+            if !keys.is_empty() {
+                bindings.keys[action.index()] = keys;
+            }
+        }
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arrows_and_wasd_are_default() {
+        let bindings = Bindings::new();
+        let keys = bindings.active_keys(|key| key == KeyCode::Up || key == KeyCode::A);
+        assert! {keys.up && keys.left}
+        assert! {!keys.down && !keys.right && !keys.pause}
+    }
+
+    #[test]
+    fn bindings_round_trip_through_storage() {
+        let mut bindings = Bindings::new();
+        bindings.toggle(Action::Restart, KeyCode::Backspace);
+        bindings.toggle(Action::Accelerate, KeyCode::W);
+        bindings.save();
+        assert_eq! {Bindings::load(), bindings}
+        assert_eq! {bindings.get_label(Action::Accelerate), "Up"}
+        assert_eq! {bindings.get_label(Action::Restart), "R|Backspace"}
+    }
+
+    #[test]
+    fn toggle_moves_key_between_actions() {
+        let mut bindings = Bindings::new();
+        bindings.toggle(Action::Restart, KeyCode::Up);
+        assert_eq! {bindings.get_keys(Action::Accelerate), &[KeyCode::W]}
+        assert_eq! {bindings.get_keys(Action::Restart), &[KeyCode::R, KeyCode::Up]}
+    }
+
+    #[test]
+    fn no_action_is_left_unbound() {
+        let mut bindings = Bindings::new();
+        bindings.toggle(Action::Restart, KeyCode::R);
+        assert_eq! {bindings.get_keys(Action::Restart), &[KeyCode::R]}
+        bindings.toggle(Action::Accelerate, KeyCode::R);
+        assert_eq! {bindings.get_keys(Action::Accelerate), &[KeyCode::W, KeyCode::Up]}
+    }
+
+    #[test]
+    fn bad_bindings_text_falls_back_to_defaults() {
+        let bindings = Bindings::from_text("left = Nope\nright = Q\njump = Space\ngarbage");
+        assert_eq! {bindings.get_keys(Action::Left), Bindings::new().get_keys(Action::Left)}
+        assert_eq! {bindings.get_keys(Action::Right), &[KeyCode::Q]}
+    }
+
+    #[test]
+    fn saves_from_before_an_action_existed_keep_its_default() {
+        let bindings = Bindings::from_text("restart = Backspace\n");
+        assert_eq! {bindings.get_keys(Action::Camera), &[KeyCode::C]}
+        let keys = bindings.active_keys(|key| key == KeyCode::C);
+        assert! {keys.camera && !keys.confirm}
+    }
+}
@@ -0,0 +1,184 @@
+use super::bindings;
+use super::choices;
+use super::engine;
+
+const TITLE_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -150.0,
+    y: 210.0,
+};
+
+const OPTIONS_Y_STEP: f32 = -22.0;
+
+const OPTIONS_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -100.0,
+    y: 160.0,
+};
+
+const HINT_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -160.0,
+    y: OPTIONS_ANCHOR.y + 12.0 * OPTIONS_Y_STEP,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum BindingsChoice {
+    Remap(bindings::Action),
+    ResetToDefaults,
+    Back,
+}
+
+const CHOICES: [(BindingsChoice, &str); 11] = [
+    (
+        BindingsChoice::Remap(bindings::Action::Accelerate),
+        "accelerate",
+    ),
+    (
+        BindingsChoice::Remap(bindings::Action::Decelerate),
+        "decelerate",
+    ),
+    (BindingsChoice::Remap(bindings::Action::Left), "left"),
+    (BindingsChoice::Remap(bindings::Action::Right), "right"),
+    (BindingsChoice::Remap(bindings::Action::Pause), "pause"),
+    (BindingsChoice::Remap(bindings::Action::Restart), "restart"),
+    (BindingsChoice::Remap(bindings::Action::Confirm), "confirm"),
+    (BindingsChoice::Remap(bindings::Action::Hop), "hop"),
+    (BindingsChoice::Remap(bindings::Action::Camera), "camera"),
+    (BindingsChoice::ResetToDefaults, "Reset to defaults"),
+    (BindingsChoice::Back, "Back"),
+];
+
+// Confirming an action waits for the next key, which is added to or removed from it
+pub struct BindingsMenu {
+    choices: choices::ChoiceList<BindingsChoice>,
+    capturing: Option<bindings::Action>,
+    pressed_key: Option<engine::KeyCode>,
+}
+
+impl BindingsMenu {
+    pub fn new(active_keys: &engine::MoveKeys) -> BindingsMenu {
+        BindingsMenu {
+            choices: choices::ChoiceList::new(&CHOICES, active_keys),
+            capturing: None,
+            pressed_key: None,
+        }
+    }
+    pub fn key_pressed(&mut self, key: engine::KeyCode) {
+        if self.capturing.is_some() && bindings::is_bindable(key) {
+            self.pressed_key = Some(key);
+        }
+    }
+    // Returns true once the player goes back, bindings are saved on every change
+    pub fn update(
+        &mut self,
+        active_keys: &engine::MoveKeys,
+        bindings: &mut bindings::Bindings,
+    ) -> bool {
+        if let Some(action) = self.capturing {
+            // The captured key may also move through the list, it is ignored until released
+            self.choices.ignore_held_keys(active_keys);
+            if let Some(key) = self.pressed_key.take() {
+                bindings.toggle(action, key);
+                bindings.save();
+                self.capturing = None;
+            }
+            return false;
+        }
+        match self.choices.update(active_keys) {
+            Some(BindingsChoice::Remap(action)) => self.capturing = Some(action),
+            Some(BindingsChoice::ResetToDefaults) => {
+                *bindings = bindings::Bindings::new();
+                bindings.save();
+            }
+            Some(BindingsChoice::Back) => return true,
+            None => {}
+        }
+        false
+    }
+    pub fn draw(&self, bindings: &bindings::Bindings) {
+        engine::draw_text("Controls", TITLE_ANCHOR, engine::TEXT_TITLE);
+        self.choices
+            .draw_with_details(OPTIONS_ANCHOR, OPTIONS_Y_STEP, |choice| match choice {
+                BindingsChoice::Remap(action) => format! {": {}", bindings.get_label(action)},
+                _ => String::new(),
+            });
+        let hint = match self.capturing {
+            Some(action) => format! {"Press a key to add to or remove from {}", action.get_name()},
+            None => {
+                format! {"[{}]: Add or remove a key", bindings.get_label(bindings::Action::Confirm)}
+            }
+        };
+        engine::draw_text(&hint, HINT_ANCHOR, engine::TEXT_DEFAULT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
+
+    const UP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        up: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const CONFIRM_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn pressed_key_is_bound_and_saved() {
+        let mut bindings = bindings::Bindings::new();
+        let mut menu = BindingsMenu::new(&NO_PRESS);
+        menu.update(&CONFIRM_PRESS, &mut bindings);
+        menu.update(&NO_PRESS, &mut bindings);
+        menu.key_pressed(engine::KeyCode::I);
+        menu.update(&NO_PRESS, &mut bindings);
+        assert_eq! {
+            bindings.get_keys(bindings::Action::Accelerate),
+            &[engine::KeyCode::W, engine::KeyCode::Up, engine::KeyCode::I]
+        }
+        assert_eq! {bindings::Bindings::load(), bindings}
+    }
+
+    #[test]
+    fn captured_key_does_not_move_selection() {
+        let mut bindings = bindings::Bindings::new();
+        let mut menu = BindingsMenu::new(&NO_PRESS);
+        menu.update(&CONFIRM_PRESS, &mut bindings);
+        menu.update(&NO_PRESS, &mut bindings);
+        menu.key_pressed(engine::KeyCode::W);
+        menu.update(&UP_PRESS, &mut bindings);
+        menu.update(&UP_PRESS, &mut bindings);
+        menu.update(&NO_PRESS, &mut bindings);
+        menu.update(&CONFIRM_PRESS, &mut bindings);
+        assert_eq! {menu.capturing, Some(bindings::Action::Accelerate)}
+    }
+
+    #[test]
+    fn keys_are_ignored_unless_capturing() {
+        let mut bindings = bindings::Bindings::new();
+        let mut menu = BindingsMenu::new(&NO_PRESS);
+        menu.key_pressed(engine::KeyCode::I);
+        menu.update(&NO_PRESS, &mut bindings);
+        assert_eq! {bindings, bindings::Bindings::new()}
+    }
+
+    #[test]
+    fn back_leaves_the_menu() {
+        let mut bindings = bindings::Bindings::new();
+        let mut menu = BindingsMenu::new(&NO_PRESS);
+        menu.update(&UP_PRESS, &mut bindings);
+        assert! {menu.update(&CONFIRM_PRESS, &mut bindings)}
+    }
+
+    #[test]
+    fn draws_bound_keys() {
+        let frame = recording::install_recorder();
+        BindingsMenu::new(&NO_PRESS).draw(&bindings::Bindings::new());
+        let frame = frame.borrow();
+        assert! {frame.has_text("> accelerate: W|Up")}
+        assert! {frame.has_text("  restart: R")}
+    }
+}
@@ -0,0 +1,339 @@
+use super::projection;
+use super::tuning;
+
+// Exponential rates per second, higher catches up faster
+const FOLLOW_RATE: f32 = 8.0;
+const FOV_EASE_RATE: f32 = 3.0;
+// The camera leads sideways motion by where the ship will be this much later
+const LOOK_AHEAD_SECONDS: f32 = 0.15;
+const SHAKE_AMPLITUDE: f32 = 15.0;
+const SHAKE_FREQUENCY_HZ: f32 = 18.0;
+const SHAKE_DECAY_RATE: f32 = 5.0;
+// High enough to look down on the fences, zoomed out to show the whole map width
+const TOP_DOWN_LENS: projection::Lens = projection::Lens {
+    eye_height: 1000.0,
+    eye_behind: 0.0,
+    orthographic_scale: Some(0.25),
+};
+// Centred ahead of the ship, which sits in the lower part of the screen
+const TOP_DOWN_LEAD: f32 = 600.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CameraMode {
+    Chase,
+    // From the ship's nose, banking with it
+    Cockpit,
+    // Straight down on the course, for checking layouts
+    TopDown,
+}
+
+impl CameraMode {
+    pub fn next(&self) -> CameraMode {
+        match self {
+            CameraMode::Chase => CameraMode::Cockpit,
+            CameraMode::Cockpit => CameraMode::TopDown,
+            CameraMode::TopDown => CameraMode::Chase,
+        }
+    }
+}
+
+// What the camera follows, taken from the player every step
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CameraTarget {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub nose_z: f32,
+    pub lateral_speed: f32,
+    pub speed_pu: f32,
+    pub roll: f32,
+}
+
+// Sits between the player and the projection, so the chase view trails the ship instead of
+// being bolted to it. Forward motion is followed exactly, the ship would drift on screen otherwise.
+#[derive(Clone)]
+pub struct CameraRig {
+    target: CameraTarget,
+    previous_target: CameraTarget,
+    x: f32,
+    previous_x: f32,
+    fov_pu: f32,
+    previous_fov_pu: f32,
+    shake_amplitude: f32,
+    shake_time: f32,
+    mode: CameraMode,
+    params: tuning::CameraParams,
+}
+
+impl CameraRig {
+    // Starts settled on the target
+    pub fn new(target: CameraTarget, params: tuning::CameraParams) -> CameraRig {
+        CameraRig {
+            target,
+            previous_target: target,
+            x: target.x,
+            previous_x: target.x,
+            fov_pu: target.speed_pu,
+            previous_fov_pu: target.speed_pu,
+            shake_amplitude: 0.0,
+            shake_time: 0.0,
+            mode: CameraMode::Chase,
+            params,
+        }
+    }
+
+    pub fn set_params(&mut self, params: tuning::CameraParams) {
+        self.params = params;
+    }
+
+    pub fn get_mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+    }
+
+    pub fn update(&mut self, target: CameraTarget, delta_t: f32) {
+        self.previous_target = self.target;
+        self.target = target;
+        self.ease_towards_target(delta_t);
+    }
+
+    // Keeps easing towards the last target, e.g. while the game-over screen is up
+    pub fn settle(&mut self, delta_t: f32) {
+        self.previous_target = self.target;
+        self.ease_towards_target(delta_t);
+    }
+
+    fn ease_towards_target(&mut self, delta_t: f32) {
+        self.previous_x = self.x;
+        self.previous_fov_pu = self.fov_pu;
+        let look_ahead_x = self.target.x + LOOK_AHEAD_SECONDS * self.target.lateral_speed;
+        self.x += (look_ahead_x - self.x) * ease(FOLLOW_RATE, delta_t);
+        self.fov_pu += (self.target.speed_pu - self.fov_pu) * ease(FOV_EASE_RATE, delta_t);
+        self.shake_time += delta_t;
+    }
+
+    pub fn shake(&mut self) {
+        self.shake_amplitude = SHAKE_AMPLITUDE;
+        self.shake_time = 0.0;
+    }
+
+    fn shake_offset(&self) -> f32 {
+        let phase = std::f32::consts::TAU * SHAKE_FREQUENCY_HZ * self.shake_time;
+        self.shake_amplitude * (-SHAKE_DECAY_RATE * self.shake_time).exp() * phase.sin()
+    }
+
+    // `alpha` of the way from the previous step, like the player.
+    // The ship's roll is eased already, so it is followed as is.
+    pub fn apply(&self, projection: &mut projection::Projection, alpha: f32) {
+        let previous = &self.previous_target;
+        let z = lerp(previous.z, self.target.z, alpha);
+        let roll = lerp(previous.roll, self.target.roll, alpha);
+        let x = lerp(self.previous_x, self.x, alpha) + self.shake_offset();
+        projection.set_fov(lerp(self.previous_fov_pu, self.fov_pu, alpha));
+        match self.mode {
+            CameraMode::Chase => {
+                projection.set_lens(projection::Lens::CHASE);
+                projection.set_offset(x, z);
+                projection.set_orientation(0.0, 0.0, roll * self.params.roll_share);
+            }
+            CameraMode::Cockpit => {
+                projection.set_lens(projection::Lens {
+                    eye_height: lerp(previous.y, self.target.y, alpha),
+                    eye_behind: 0.0,
+                    orthographic_scale: None,
+                });
+                let ship_x = lerp(previous.x, self.target.x, alpha) + self.shake_offset();
+                projection.set_offset(ship_x, lerp(previous.nose_z, self.target.nose_z, alpha));
+                projection.set_orientation(0.0, 0.0, roll);
+            }
+            CameraMode::TopDown => {
+                projection.set_lens(TOP_DOWN_LENS);
+                projection.set_offset(x, z + TOP_DOWN_LEAD);
+                projection.set_orientation(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+            }
+        }
+    }
+}
+
+fn lerp(from: f32, to: f32, alpha: f32) -> f32 {
+    from + (to - from) * alpha
+}
+
+// Share of the remaining distance covered in `delta_t`, independent of the step size
+fn ease(rate: f32, delta_t: f32) -> f32 {
+    1.0 - (-rate * delta_t).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const STEP: f32 = 1.0 / 50.0;
+
+    const AT_REST: CameraTarget = CameraTarget {
+        x: 0.0,
+        y: -176.0,
+        z: 25.0,
+        nose_z: 30.0,
+        lateral_speed: 0.0,
+        speed_pu: 0.0,
+        roll: 0.0,
+    };
+
+    fn offset_x(rig: &CameraRig) -> f32 {
+        let mut projection = projection::Projection::new(200.0);
+        rig.apply(&mut projection, 1.0);
+        projection.offset_x
+    }
+
+    fn run_for(rig: &mut CameraRig, target: CameraTarget, seconds: f32) {
+        for _ in 0..(seconds / STEP).round() as usize {
+            rig.update(target, STEP);
+        }
+    }
+
+    #[test]
+    fn follows_sideways_with_a_lag() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        let moved = CameraTarget {
+            x: 100.0,
+            ..AT_REST
+        };
+        rig.update(moved, STEP);
+        assert! {offset_x(&rig) > 0.0 && offset_x(&rig) < 50.0}
+        run_for(&mut rig, moved, 2.0);
+        assert! {(offset_x(&rig) - 100.0).abs() < 0.01}
+    }
+
+    #[test]
+    fn follows_forward_exactly() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        rig.update(CameraTarget { z: 80.0, ..AT_REST }, STEP);
+        let mut projection = projection::Projection::new(200.0);
+        rig.apply(&mut projection, 1.0);
+        assert_eq! {projection.offset_z, 80.0}
+        rig.apply(&mut projection, 0.5);
+        assert_eq! {projection.offset_z, 52.5}
+    }
+
+    #[test]
+    fn looks_ahead_of_sideways_motion() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        let drifting = CameraTarget {
+            lateral_speed: -400.0,
+            ..AT_REST
+        };
+        run_for(&mut rig, drifting, 2.0);
+        assert! {(offset_x(&rig) + LOOK_AHEAD_SECONDS * 400.0).abs() < 0.01}
+    }
+
+    #[test]
+    fn fov_eases_with_speed() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        let fast = CameraTarget {
+            speed_pu: 1.0,
+            ..AT_REST
+        };
+        let mut eased = projection::Projection::new(200.0);
+        let mut instant = projection::Projection::new(200.0);
+        rig.update(fast, STEP);
+        rig.apply(&mut eased, 1.0);
+        instant.set_fov(1.0);
+        let mut start = projection::Projection::new(200.0);
+        start.set_fov(0.0);
+        assert! {eased.get_fov() < start.get_fov() && eased.get_fov() > instant.get_fov()}
+        run_for(&mut rig, fast, 5.0);
+        rig.apply(&mut eased, 1.0);
+        assert! {(eased.get_fov() - instant.get_fov()).abs() < 0.01}
+    }
+
+    #[test]
+    fn shake_dies_down() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        rig.shake();
+        let mut largest: f32 = 0.0;
+        for _ in 0..10 {
+            rig.settle(STEP / 4.0);
+            largest = largest.max(offset_x(&rig).abs());
+        }
+        assert! {largest > 0.50 * SHAKE_AMPLITUDE}
+        run_for(&mut rig, AT_REST, 2.0);
+        assert! {offset_x(&rig).abs() < 0.01}
+    }
+
+    #[test]
+    fn horizon_banks_by_the_roll_share() {
+        let banked = CameraTarget {
+            roll: 0.40,
+            ..AT_REST
+        };
+        let mut projection = projection::Projection::new(200.0);
+        let mut rig = CameraRig::new(banked, tuning::CameraParams::DEFAULT);
+        rig.apply(&mut projection, 1.0);
+        assert_eq! {projection.get_orientation(), (0.0, 0.0, 0.0)}
+        rig.set_params(tuning::CameraParams { roll_share: 0.50 });
+        rig.update(AT_REST, STEP);
+        rig.apply(&mut projection, 0.50);
+        assert! {(projection.get_orientation().2 - 0.10).abs() < 1e-6}
+    }
+
+    #[test]
+    fn modes_cycle_back_to_chase() {
+        let mut mode = CameraMode::Chase;
+        for expected in [CameraMode::Cockpit, CameraMode::TopDown, CameraMode::Chase] {
+            mode = mode.next();
+            assert_eq! {mode, expected}
+        }
+    }
+
+    fn applied(rig: &CameraRig) -> projection::Projection {
+        recording::install_recorder();
+        let mut projection = projection::Projection::new(200.0);
+        rig.apply(&mut projection, 1.0);
+        projection
+    }
+
+    #[test]
+    fn cockpit_looks_from_the_nose_and_banks_with_the_ship() {
+        let banked = CameraTarget {
+            x: 40.0,
+            roll: 0.30,
+            ..AT_REST
+        };
+        let mut rig = CameraRig::new(banked, tuning::CameraParams::DEFAULT);
+        rig.set_mode(CameraMode::Cockpit);
+        let projection = applied(&rig);
+        assert_eq! {projection.get_orientation(), (0.0, 0.0, 0.30)}
+        let ahead = projection::Point3D::new(40.0, banked.y, 2000.0);
+        let centre = projection.project_point(&ahead).unwrap();
+        assert! {centre.x.abs() < 1e-3 && centre.y.abs() < 1e-3}
+        // The ship's own body is behind the near plane
+        let tail = projection::Point3D::new(40.0, banked.y, banked.z);
+        assert_eq! {projection.project_point(&tail), None}
+    }
+
+    #[test]
+    fn top_down_keeps_sizes_along_the_course() {
+        let mut rig = CameraRig::new(AT_REST, tuning::CameraParams::DEFAULT);
+        rig.set_mode(CameraMode::TopDown);
+        let projection = applied(&rig);
+        let ground = AT_REST.y - 20.0;
+        let project = |x: f32, z: f32| {
+            projection
+                .project_point(&projection::Point3D::new(x, ground, z))
+                .unwrap()
+        };
+        let ship = project(0.0, AT_REST.z);
+        assert! {ship.x.abs() < 1e-3 && ship.y < 0.0}
+        let near_width = project(100.0, AT_REST.z).x - ship.x;
+        let far_width = project(100.0, AT_REST.z + 1000.0).x - project(0.0, AT_REST.z + 1000.0).x;
+        assert! {(near_width - far_width).abs() < 1e-3}
+        assert! {project(0.0, AT_REST.z + 100.0).y > ship.y}
+        // What was behind the chase view is in sight
+        assert! {projection.get_view_zone_z_range().0 < AT_REST.z}
+    }
+}
@@ -0,0 +1,203 @@
+use super::engine;
+
+const MARKER: &str = ">";
+// Share of the touch drag range that moves the highlight one row
+const SWIPE_THRESHOLD: f32 = 0.50;
+
+// Vertical list of options picked with up/down and confirm, shared by the overlay menus
+pub struct ChoiceList<T: Copy + 'static> {
+    choices: &'static [(T, &'static str)],
+    selected: usize,
+    previous_keys: engine::MoveKeys,
+    // A touch that began with the list open, it picks when lifted unless it was dragged
+    tap_pending: bool,
+}
+
+impl<T: Copy + 'static> ChoiceList<T> {
+    // Keys already held when the list opens only count once released
+    pub fn new(
+        choices: &'static [(T, &'static str)],
+        active_keys: &engine::MoveKeys,
+    ) -> ChoiceList<T> {
+        ChoiceList {
+            choices,
+            selected: 0,
+            previous_keys: *active_keys,
+            tap_pending: false,
+        }
+    }
+    pub fn update(&mut self, active_keys: &engine::MoveKeys) -> Option<T> {
+        let previous = self.previous_keys;
+        self.previous_keys = *active_keys;
+        let n_choices = self.choices.len();
+        // Touch screens have no arrows, a drag up or down moves the highlight instead
+        if active_keys.touch && !previous.touch {
+            self.tap_pending = true;
+        }
+        let swipe = if self.tap_pending && active_keys.touch {
+            active_keys.throttle_axis
+        } else {
+            0.0
+        };
+        if swipe.abs() >= SWIPE_THRESHOLD {
+            self.tap_pending = false;
+        }
+        if (active_keys.up && !previous.up) || swipe >= SWIPE_THRESHOLD {
+            self.selected = (self.selected + n_choices - 1) % n_choices;
+        }
+        if (active_keys.down && !previous.down) || swipe <= -SWIPE_THRESHOLD {
+            self.selected = (self.selected + 1) % n_choices;
+        }
+        // Nor a confirm key, lifting a tap picks the highlighted choice
+        let tapped = self.tap_pending && !active_keys.touch;
+        if !active_keys.touch {
+            self.tap_pending = false;
+        }
+        if (active_keys.confirm && !previous.confirm) || tapped {
+            return Some(self.choices[self.selected].0);
+        }
+        None
+    }
+    // Keys held now only count once released, as when the list opened
+    pub fn ignore_held_keys(&mut self, active_keys: &engine::MoveKeys) {
+        self.previous_keys = *active_keys;
+        self.tap_pending = false;
+    }
+    pub fn draw(&self, first_anchor: engine::PointScreen, y_step: f32) {
+        self.draw_with_details(first_anchor, y_step, |_| String::new());
+    }
+    // Each label is followed by the text `details` gives for its choice
+    pub fn draw_with_details(
+        &self,
+        first_anchor: engine::PointScreen,
+        y_step: f32,
+        details: impl Fn(T) -> String,
+    ) {
+        for (index, (choice, label)) in self.choices.iter().enumerate() {
+            let marker = if index == self.selected { MARKER } else { " " };
+            let anchor = engine::PointScreen {
+                x: first_anchor.x,
+                y: first_anchor.y + index as f32 * y_step,
+            };
+            let message = format! {"{} {}{}", marker, label, details(*choice)};
+            engine::draw_text(&message, anchor, engine::TEXT_INSTRUCTIONS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const CHOICES: [(u32, &str); 3] = [(1, "One"), (2, "Two"), (3, "Three")];
+
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
+
+    const DOWN_PRESS: engine::MoveKeys = engine::MoveKeys {
+        down: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const UP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        up: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const CONFIRM_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn confirm_picks_selected_choice() {
+        let mut list = ChoiceList::new(&CHOICES, &NO_PRESS);
+        list.update(&DOWN_PRESS);
+        assert_eq! {list.update(&CONFIRM_PRESS), Some(2)}
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut list = ChoiceList::new(&CHOICES, &NO_PRESS);
+        list.update(&UP_PRESS);
+        assert_eq! {list.update(&CONFIRM_PRESS), Some(3)}
+    }
+
+    #[test]
+    fn held_confirm_is_ignored() {
+        let mut list = ChoiceList::new(&CHOICES, &CONFIRM_PRESS);
+        assert_eq! {list.update(&CONFIRM_PRESS), None}
+        assert_eq! {list.update(&NO_PRESS), None}
+        assert_eq! {list.update(&CONFIRM_PRESS), Some(1)}
+    }
+
+    const TOUCH: engine::MoveKeys = engine::MoveKeys {
+        touch: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    fn touch_dragged(throttle_axis: f32) -> engine::MoveKeys {
+        engine::MoveKeys {
+            throttle_axis,
+            ..TOUCH
+        }
+    }
+
+    #[test]
+    fn lifted_tap_picks_selected_choice() {
+        let mut list = ChoiceList::new(&CHOICES, &NO_PRESS);
+        list.update(&DOWN_PRESS);
+        assert_eq! {list.update(&TOUCH), None}
+        assert_eq! {list.update(&TOUCH), None}
+        assert_eq! {list.update(&NO_PRESS), Some(2)}
+    }
+
+    #[test]
+    fn drags_move_the_highlight_one_row_each() {
+        let mut list = ChoiceList::new(&CHOICES, &NO_PRESS);
+        list.update(&TOUCH);
+        list.update(&touch_dragged(-0.30));
+        list.update(&touch_dragged(-0.60));
+        list.update(&touch_dragged(-1.0));
+        assert_eq! {list.update(&NO_PRESS), None}
+        list.update(&touch_dragged(0.0));
+        list.update(&touch_dragged(-0.60));
+        list.update(&NO_PRESS);
+        list.update(&TOUCH);
+        list.update(&touch_dragged(0.70));
+        list.update(&NO_PRESS);
+        list.update(&TOUCH);
+        assert_eq! {list.update(&NO_PRESS), Some(2)}
+    }
+
+    #[test]
+    fn touch_held_when_opened_is_ignored() {
+        let mut list = ChoiceList::new(&CHOICES, &TOUCH);
+        assert_eq! {list.update(&touch_dragged(-1.0)), None}
+        assert_eq! {list.update(&NO_PRESS), None}
+        list.update(&TOUCH);
+        assert_eq! {list.update(&NO_PRESS), Some(1)}
+    }
+
+    #[test]
+    fn draws_options_with_marker() {
+        let frame = recording::install_recorder();
+        ChoiceList::new(&CHOICES, &NO_PRESS).draw(engine::PointScreen { x: 0.0, y: 0.0 }, -20.0);
+        let frame = frame.borrow();
+        assert! {frame.has_text("> One")}
+        assert! {frame.has_text("  Two")}
+    }
+
+    #[test]
+    fn details_follow_labels() {
+        let frame = recording::install_recorder();
+        ChoiceList::new(&CHOICES, &NO_PRESS).draw_with_details(
+            engine::PointScreen { x: 0.0, y: 0.0 },
+            -20.0,
+            |value| format! {": {}", value},
+        );
+        let frame = frame.borrow();
+        assert! {frame.has_text("> One: 1")}
+        assert! {frame.has_text("  Three: 3")}
+    }
+}
@@ -1,20 +1,28 @@
+use super::bindings;
+use super::renderer;
+use std::cell::RefCell;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct PointScreen {
     pub x: f32,
     pub y: f32,
 }
 
 // Graphics
-pub const BACKGROUND_COLOR: macroquad::prelude::Color = macroquad::prelude::BLACK;
+pub type Color = macroquad::prelude::Color;
+
+pub const BACKGROUND_COLOR: Color = macroquad::prelude::BLACK;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct DrawParameters {
-    color: macroquad::prelude::Color,
-    line_width: f32,
+    pub color: Color,
+    pub line_width: f32,
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct TextParameters {
-    color: macroquad::prelude::Color,
-    font_size: f32,
+    pub color: Color,
+    pub font_size: f32,
 }
 
 pub const DEFAULT_LINE: DrawParameters = DrawParameters {
@@ -27,6 +35,16 @@ pub const PHOENIX_LINE: DrawParameters = DrawParameters {
     line_width: 2.0,
 };
 
+pub const GHOST_LINE: DrawParameters = DrawParameters {
+    color: Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 0.35,
+    },
+    line_width: 2.0,
+};
+
 pub const GRID_LINE: DrawParameters = DrawParameters {
     color: macroquad::prelude::GRAY,
     line_width: 0.25,
@@ -52,25 +70,27 @@ pub const TEXT_INSTRUCTIONS: TextParameters = TextParameters {
     font_size: 20.0,
 };
 
-pub fn clear_background() {
-    macroquad::prelude::clear_background(BACKGROUND_COLOR);
+thread_local! {
+    static RENDERER: RefCell<Box<dyn renderer::Renderer>> =
+        RefCell::new(renderer::default_renderer());
 }
 
-pub fn draw_line(p1: PointScreen, p2: PointScreen) {
-    draw_line_personalized(p1, p2, DEFAULT_LINE);
+pub fn set_renderer(new_renderer: Box<dyn renderer::Renderer>) -> Box<dyn renderer::Renderer> {
+    RENDERER.with(|current| current.replace(new_renderer))
+}
+
+fn with_renderer<T>(action: impl FnOnce(&mut dyn renderer::Renderer) -> T) -> T {
+    RENDERER.with(|current| action(current.borrow_mut().as_mut()))
+}
+
+pub fn clear_background() {
+    with_renderer(|r| r.clear(BACKGROUND_COLOR));
 }
 
 pub fn draw_line_personalized(p1: PointScreen, p2: PointScreen, draw_params: DrawParameters) {
     let pt1 = transform(p1);
     let pt2 = transform(p2);
-    macroquad::shapes::draw_line(
-        pt1.x,
-        pt1.y,
-        pt2.x,
-        pt2.y,
-        draw_params.line_width,
-        draw_params.color,
-    );
+    with_renderer(|r| r.draw_line(&pt1, &pt2, &draw_params));
 }
 
 pub fn transform(p: PointScreen) -> PointScreen {
@@ -80,12 +100,19 @@ pub fn transform(p: PointScreen) -> PointScreen {
     }
 }
 
+pub fn inverse_transform(p: PointScreen) -> PointScreen {
+    PointScreen {
+        x: p.x - get_screen_width() * 0.50,
+        y: -p.y + get_screen_height() * 0.50,
+    }
+}
+
 pub fn get_screen_height() -> f32 {
-    macroquad::prelude::screen_height()
+    with_renderer(|r| r.screen_size().1)
 }
 
 pub fn get_screen_width() -> f32 {
-    macroquad::prelude::screen_width()
+    with_renderer(|r| r.screen_size().0)
 }
 
 // Time
@@ -98,33 +125,152 @@ pub fn get_time() -> f64 {
 }
 
 // Input
+// Digital keys drive the menus, steering and throttle also take analog axes in [-1, 1]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct MoveKeys {
     pub up: bool,
     pub down: bool,
     pub left: bool,
     pub right: bool,
+    pub pause: bool,
+    pub restart: bool,
+    pub confirm: bool,
+    pub hop: bool,
+    // Only changes the view, replays do not store it
+    pub camera: bool,
+    pub touch: bool,
+    pub steer_axis: f32,
+    pub throttle_axis: f32,
 }
 
-pub fn get_active_move_keys() -> MoveKeys {
-    MoveKeys {
-        up: macroquad::prelude::is_key_down(macroquad::prelude::KeyCode::W),
-        down: macroquad::prelude::is_key_down(macroquad::prelude::KeyCode::S),
-        left: macroquad::prelude::is_key_down(macroquad::prelude::KeyCode::A),
-        right: macroquad::prelude::is_key_down(macroquad::prelude::KeyCode::D),
+impl MoveKeys {
+    pub const NONE: MoveKeys = MoveKeys {
+        up: false,
+        down: false,
+        left: false,
+        right: false,
+        pause: false,
+        restart: false,
+        confirm: false,
+        hop: false,
+        camera: false,
+        touch: false,
+        steer_axis: 0.0,
+        throttle_axis: 0.0,
+    };
+
+    // Keyboard keys count as a full deflection of the axis
+    pub fn get_steer(&self) -> f32 {
+        (self.steer_axis + self.right as i32 as f32 - self.left as i32 as f32).clamp(-1.0, 1.0)
+    }
+
+    pub fn get_throttle(&self) -> f32 {
+        (self.throttle_axis + self.up as i32 as f32 - self.down as i32 as f32).clamp(-1.0, 1.0)
+    }
+
+    pub fn merge(&self, other: &MoveKeys) -> MoveKeys {
+        MoveKeys {
+            up: self.up || other.up,
+            down: self.down || other.down,
+            left: self.left || other.left,
+            right: self.right || other.right,
+            pause: self.pause || other.pause,
+            restart: self.restart || other.restart,
+            confirm: self.confirm || other.confirm,
+            hop: self.hop || other.hop,
+            camera: self.camera || other.camera,
+            touch: self.touch || other.touch,
+            steer_axis: (self.steer_axis + other.steer_axis).clamp(-1.0, 1.0),
+            throttle_axis: (self.throttle_axis + other.throttle_axis).clamp(-1.0, 1.0),
+        }
     }
 }
 
+// Axis values are kept to steps a replay stores exactly
+pub const AXIS_STEPS: f32 = 127.0;
+
+pub fn quantize_axis(value: f32) -> f32 {
+    (value.clamp(-1.0, 1.0) * AXIS_STEPS).round() / AXIS_STEPS
+}
+
+pub type KeyCode = macroquad::prelude::KeyCode;
+
+pub fn get_active_move_keys(bindings: &bindings::Bindings) -> MoveKeys {
+    bindings.active_keys(macroquad::prelude::is_key_down)
+}
+
+pub fn get_last_key_pressed() -> Option<KeyCode> {
+    macroquad::prelude::get_last_key_pressed()
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Touch {
+    pub id: u64,
+    pub position: PointScreen,
+}
+
+// Fingers currently on the screen, in the same coordinates used for drawing
+pub fn get_touches() -> Vec<Touch> {
+    macroquad::input::touches()
+        .iter()
+        .filter(|touch| {
+            !matches!(
+                touch.phase,
+                macroquad::input::TouchPhase::Ended | macroquad::input::TouchPhase::Cancelled
+            )
+        })
+        .map(|touch| Touch {
+            id: touch.id,
+            position: inverse_transform(PointScreen {
+                x: touch.position.x,
+                y: touch.position.y,
+            }),
+        })
+        .collect()
+}
+
 // Random
-//pub fn set_rand_seed(seed: u64) {
-//    macroquad::rand::srand(seed);
-//}
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const INCREMENT: u64 = 1442695040888963407;
+
+    pub fn new(seed: u64) -> Rng {
+        let mut rng = Rng { state: 0 };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state
+            .wrapping_mul(Rng::MULTIPLIER)
+            .wrapping_add(Rng::INCREMENT);
+        let xorshifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    // Never returns `high`, 24 bits are all an f32 holds below 1
+    pub fn gen_range(&mut self, low: f32, high: f32) -> f32 {
+        let r = (self.next_u32() >> 8) as f32 / (1u32 << 24) as f32;
+        low + (high - low) * r
+    }
+}
+
+pub const MAX_SEED: u64 = 1_000_000;
 
-pub fn gen_range<T: macroquad::rand::RandomRange>(low: T, high: T) -> T {
-    macroquad::rand::gen_range::<T>(low, high)
+pub fn get_start_seed() -> u64 {
+    (macroquad::miniquad::date::now() * 1000.0) as u64 % MAX_SEED
 }
 
 // text
 pub fn draw_text(message: &str, p: PointScreen, params: TextParameters) {
     let pt = transform(p);
-    macroquad::prelude::draw_text(message, pt.x, pt.y, params.font_size, params.color);
+    with_renderer(|r| r.draw_text(message, &pt, &params));
 }
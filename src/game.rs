@@ -1,15 +1,27 @@
+use crate::bindings;
+use crate::bindingsmenu;
 use crate::engine;
+use crate::gameover;
 use crate::level;
+use crate::map;
+use crate::mapfile;
+use crate::pausemenu;
+use crate::replay;
 use crate::startmenu;
+use crate::tuning;
 
 const FRAME_UPDATE_SECONDS: f64 = 1.0 / 50.0;
+const MAX_CATCHUP_STEPS: u32 = 5;
+// Choices on the game-over screen are ignored at first, so a key mashed at the crash does not skip it
 const GAMEOVER_TIME_SECONDS: f64 = 1.0;
 
 #[derive(PartialEq, Debug)]
 pub enum GameMode {
     StartMenu,
+    Bindings,
     StartToLevel,
     Level,
+    Paused,
     ToGameOver,
     GameOver,
 }
@@ -18,82 +30,304 @@ pub struct Game {
     mode: GameMode,
     level: level::Level,
     menu: startmenu::StartMenu,
+    bindings: bindings::Bindings,
+    bindings_menu: bindingsmenu::BindingsMenu,
+    pause_menu: pausemenu::PauseMenu,
+    previous_keys: engine::MoveKeys,
     last_update_time: f64,
+    simulation_time: f64,
+    step_accumulator: f64,
     gameover_timestamp: f64,
+    gameover_screen: Option<gameover::GameOverScreen>,
+    recorder: replay::Recorder,
+    recording_enabled: bool,
+    finished_recording: Option<replay::Recording>,
 }
 
 impl Game {
-    pub fn new(camera_drop: f32, map_width: f32, map_length: f32, time: f64) -> Game {
+    pub fn new(
+        camera_drop: f32,
+        map_width: f32,
+        map_length: f32,
+        seed: u64,
+        tuning: tuning::Tuning,
+        time: f64,
+    ) -> Game {
+        Game::with_level(
+            level::Level::new(camera_drop, map_width, map_length, seed, tuning, time),
+            startmenu::StartMenu::new(seed),
+            time,
+        )
+    }
+
+    pub fn from_map_file(
+        camera_drop: f32,
+        map_file: &mapfile::MapFile,
+        course: &str,
+        tuning: tuning::Tuning,
+        time: f64,
+    ) -> Game {
+        let game_map = map::Map::from_map_file(camera_drop, map_file, tuning.map);
+        let mut game = Game::with_level(
+            level::Level::from_map(camera_drop, game_map, tuning, time),
+            startmenu::StartMenu::with_course(course, map_file.seed),
+            time,
+        );
+        // Replays rebuild the map from its seed, which a hand-authored course does not follow
+        game.recording_enabled = false;
+        game
+    }
+
+    fn with_level(level: level::Level, menu: startmenu::StartMenu, time: f64) -> Game {
         Game {
             mode: GameMode::StartMenu,
-            level: level::Level::new(camera_drop, map_width, map_length, time),
-            menu: startmenu::StartMenu::new(),
+            level,
+            menu,
+            bindings: bindings::Bindings::load(),
+            bindings_menu: bindingsmenu::BindingsMenu::new(&engine::MoveKeys::NONE),
+            pause_menu: pausemenu::PauseMenu::new(&engine::MoveKeys::NONE),
+            previous_keys: engine::MoveKeys::NONE,
             last_update_time: time,
+            simulation_time: time,
+            step_accumulator: 0.0,
             gameover_timestamp: 0.0,
+            gameover_screen: None,
+            recorder: replay::Recorder::new(),
+            recording_enabled: true,
+            finished_recording: None,
         }
     }
 
-    pub fn run(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
-        if self.skip_frame(current_time) {
-            return;
+    // Replays only match when played with the tuning they were recorded with
+    pub fn replay(recording: &replay::Recording, tuning: tuning::Tuning) -> Game {
+        let header = recording.get_header();
+        let mut game = Game::new(
+            header.camera_drop,
+            header.map_width,
+            header.map_length,
+            header.seed,
+            tuning,
+            header.start_time,
+        );
+        // The first frame holds the keys that started the run, so they are not fresh presses
+        let start_keys = match recording.frames().first() {
+            Some(frame) => frame.keys,
+            None => engine::MoveKeys::NONE,
+        };
+        game.start_run(header.start_time, &start_keys);
+        game.previous_keys = start_keys;
+        game
+    }
+
+    pub fn set_tuning(&mut self, tuning: tuning::Tuning) {
+        self.level.set_tuning(tuning);
+    }
+
+    pub fn get_bindings(&self) -> &bindings::Bindings {
+        &self.bindings
+    }
+
+    // Called before `run` with the key first pressed this frame, used to remap bindings
+    pub fn key_pressed(&mut self, key: engine::KeyCode) {
+        if self.mode == GameMode::Bindings {
+            self.bindings_menu.key_pressed(key);
         }
+    }
+
+    pub fn take_finished_recording(&mut self) -> Option<replay::Recording> {
+        self.finished_recording.take()
+    }
+
+    pub fn run(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        self.recorder.record(current_time, active_keys);
+        let frame_time = current_time - self.last_update_time;
+        self.last_update_time = current_time;
         match self.mode {
             GameMode::StartMenu => self.run_start_menu(active_keys),
-            GameMode::StartToLevel => self.init_level(current_time),
-            GameMode::Level => self.run_game(current_time, active_keys),
-            GameMode::ToGameOver => self.init_gameover(current_time),
-            GameMode::GameOver => self.run_gameover(current_time),
+            GameMode::Bindings => self.run_bindings_menu(active_keys),
+            GameMode::StartToLevel => self.init_level(current_time, active_keys),
+            GameMode::Level => self.run_game(current_time, frame_time, active_keys),
+            GameMode::Paused => self.run_pause_menu(current_time, active_keys),
+            GameMode::ToGameOver => {
+                self.level.update_camera(frame_time as f32);
+                self.init_gameover(current_time, active_keys)
+            }
+            GameMode::GameOver => {
+                self.level.update_camera(frame_time as f32);
+                self.run_gameover(current_time, active_keys)
+            }
         }
+        self.previous_keys = *active_keys;
     }
 
     pub fn draw(&self) {
+        let alpha = self.get_interpolation_alpha();
         match self.mode {
             GameMode::StartMenu => {
-                self.menu.draw();
-                self.level.draw()
+                self.menu.draw(&self.bindings);
+                self.level.draw(alpha)
+            }
+            GameMode::Bindings => {
+                self.level.draw(alpha);
+                self.bindings_menu.draw(&self.bindings)
+            }
+            GameMode::StartToLevel => self.level.draw(alpha),
+            GameMode::Level => {
+                self.level.draw(alpha);
+                self.level.draw_hud(alpha)
+            }
+            GameMode::Paused => {
+                self.level.draw(alpha);
+                self.level.draw_hud(alpha);
+                self.pause_menu.draw(&self.bindings)
+            }
+            GameMode::ToGameOver => self.level.draw(alpha),
+            GameMode::GameOver => {
+                self.level.draw(alpha);
+                if let Some(screen) = &self.gameover_screen {
+                    screen.draw()
+                }
             }
-            GameMode::StartToLevel => self.level.draw(),
-            GameMode::Level => self.level.draw(),
-            GameMode::ToGameOver => self.level.draw(),
-            GameMode::GameOver => self.level.draw(),
         }
     }
 
-    fn skip_frame(&mut self, current_time: f64) -> bool {
-        if current_time - self.last_update_time >= FRAME_UPDATE_SECONDS {
-            return false;
-        }
-        true
+    fn get_interpolation_alpha(&self) -> f32 {
+        (self.step_accumulator / FRAME_UPDATE_SECONDS) as f32
     }
 
     fn run_start_menu(&mut self, active_keys: &engine::MoveKeys) {
         self.menu.update(active_keys);
+        if self.menu.get_seed() != self.level.get_seed() {
+            self.level.set_seed(self.menu.get_seed());
+        }
         if self.menu.request_level_start() {
             self.mode = GameMode::StartToLevel;
+        } else if self.menu.request_bindings() {
+            self.bindings_menu = bindingsmenu::BindingsMenu::new(active_keys);
+            self.mode = GameMode::Bindings;
         }
     }
 
-    fn init_level(&mut self, current_time: f64) {
+    fn run_bindings_menu(&mut self, active_keys: &engine::MoveKeys) {
+        if self.bindings_menu.update(active_keys, &mut self.bindings) {
+            self.menu.reopen(active_keys);
+            self.mode = GameMode::StartMenu;
+        }
+    }
+
+    fn init_level(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        self.start_run(current_time, active_keys);
+    }
+
+    fn start_run(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        self.last_update_time = current_time;
+        self.simulation_time = current_time;
+        self.step_accumulator = 0.0;
         self.level.reset(current_time);
         self.mode = GameMode::Level;
+        if !self.recording_enabled {
+            return;
+        }
+        let layout = self.level.get_map_layout();
+        self.recorder.start(replay::ReplayHeader {
+            seed: self.level.get_seed(),
+            camera_drop: layout.0,
+            map_width: layout.1,
+            map_length: layout.2,
+            start_time: current_time,
+            tuning_hash: replay::tuning_hash(&self.level.get_tuning()),
+        });
+        self.recorder.record(current_time, active_keys);
     }
 
-    fn run_game(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
-        self.level.update(current_time, active_keys);
-        if self.level.check_game_over() {
-            self.mode = GameMode::ToGameOver;
+    fn run_game(&mut self, current_time: f64, frame_time: f64, active_keys: &engine::MoveKeys) {
+        if active_keys.pause && !self.previous_keys.pause {
+            self.pause_menu = pausemenu::PauseMenu::new(active_keys);
+            self.mode = GameMode::Paused;
+            return;
+        }
+        if active_keys.restart && !self.previous_keys.restart {
+            self.start_run(current_time, active_keys);
+            return;
+        }
+        if active_keys.camera && !self.previous_keys.camera {
+            self.level.cycle_camera_mode();
+        }
+        self.step_accumulator += frame_time;
+        let mut n_steps = 0;
+        while self.step_accumulator >= FRAME_UPDATE_SECONDS {
+            if n_steps == MAX_CATCHUP_STEPS {
+                // Long hitches are dropped instead of simulated, the game slows down
+                self.step_accumulator = 0.0;
+                return;
+            }
+            self.step_accumulator -= FRAME_UPDATE_SECONDS;
+            self.simulation_time += FRAME_UPDATE_SECONDS;
+            self.level.update(self.simulation_time, active_keys);
+            if self.level.check_game_over() {
+                self.mode = GameMode::ToGameOver;
+                self.step_accumulator = 0.0;
+                return;
+            }
+            n_steps += 1;
         }
     }
 
-    fn init_gameover(&mut self, current_time: f64) {
+    // Frame time is dropped while paused, so the level resumes where it stopped
+    fn run_pause_menu(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        match self.pause_menu.update(active_keys) {
+            Some(pausemenu::PauseChoice::Resume) => {
+                self.level.phoenix.wait_for_hop_release();
+                self.mode = GameMode::Level
+            }
+            Some(pausemenu::PauseChoice::Restart) => self.start_run(current_time, active_keys),
+            Some(pausemenu::PauseChoice::QuitToMenu) => {
+                self.quit_to_menu(current_time, active_keys)
+            }
+            None => {}
+        }
+    }
+
+    fn quit_to_menu(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        self.recorder.finish();
+        self.step_accumulator = 0.0;
+        self.level.reset(current_time);
+        self.menu.reopen(active_keys);
+        self.mode = GameMode::StartMenu;
+    }
+
+    fn init_gameover(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
         self.gameover_timestamp = current_time;
         self.mode = GameMode::GameOver;
+        self.finished_recording = self.recorder.finish();
+        let summary = self.level.finish_run();
+        let allow_new_map = !self.menu.has_course();
+        self.gameover_screen = Some(gameover::GameOverScreen::new(
+            summary,
+            allow_new_map,
+            active_keys,
+        ));
     }
 
-    fn run_gameover(&mut self, current_time: f64) {
-        if current_time - self.gameover_timestamp > GAMEOVER_TIME_SECONDS {
-            self.mode = GameMode::Level;
-            self.level.reset(current_time);
+    fn run_gameover(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        let Some(screen) = &mut self.gameover_screen else {
+            return;
+        };
+        let choice = screen.update(active_keys);
+        if current_time - self.gameover_timestamp < GAMEOVER_TIME_SECONDS {
+            return;
+        }
+        match choice {
+            Some(gameover::GameOverChoice::Retry) => self.start_run(current_time, active_keys),
+            Some(gameover::GameOverChoice::NewMap) => {
+                let seed =
+                    engine::Rng::new(self.level.get_seed()).next_u32() as u64 % engine::MAX_SEED;
+                self.menu.set_seed(seed);
+                self.level.set_seed(seed);
+                self.start_run(current_time, active_keys);
+            }
+            Some(gameover::GameOverChoice::Menu) => self.quit_to_menu(current_time, active_keys),
+            None => {}
         }
     }
 }
@@ -101,29 +335,26 @@ impl Game {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::camera;
+    use crate::recording;
 
     const CAMERA_DROP: f32 = 10.0;
     const MAP_LENGTH: f32 = 100.0;
     const MAP_WIDTH: f32 = 1000.0;
+    const SEED: u64 = 1;
     const INIT_TIME: f64 = 0.0;
+    const TUNING: tuning::Tuning = tuning::Tuning::DEFAULT;
 
-    const NO_PRESS: engine::MoveKeys = engine::MoveKeys {
-        up: false,
-        down: false,
-        left: false,
-        right: false,
-    };
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
 
     const UP_PRESS: engine::MoveKeys = engine::MoveKeys {
         up: true,
-        down: false,
-        left: false,
-        right: false,
+        ..engine::MoveKeys::NONE
     };
 
     #[test]
     fn init_game() {
-        let game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
+        let game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
 
         assert_eq! { game.level.phoenix.get_position().0, 0.0}
         assert_eq! { game.level.phoenix.get_position().1, 25.0}
@@ -134,7 +365,7 @@ mod tests {
 
     #[test]
     fn init_game_start() {
-        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
         game.run(0.1, &UP_PRESS);
 
         assert_eq! { game.level.phoenix.get_position().0, 0.0}
@@ -146,7 +377,7 @@ mod tests {
 
     #[test]
     fn init_game_start_reset() {
-        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
         game.run(0.1, &NO_PRESS);
         game.run(0.2, &UP_PRESS);
         game.run(0.3, &UP_PRESS);
@@ -155,29 +386,585 @@ mod tests {
         assert_eq! { game.mode, GameMode::Level}
 
         assert_eq! { game.level.phoenix.get_position().0, 0.0}
-        assert_eq! { game.level.phoenix.get_position().1, 25.0}
+        // The last 0.1 s runs five steps, each speeding up by 20 before moving
+        let z = 25.0 + (220.0 + 240.0 + 260.0 + 280.0 + 300.0) * FRAME_UPDATE_SECONDS as f32;
+        assert! { (game.level.phoenix.get_position().1 - z).abs() < 1e-3}
 
         assert_eq! { game.level.projection.offset_x, 0.0}
-        assert_eq! { game.level.projection.offset_z, 25.0}
+        assert! { (game.level.projection.offset_z - z).abs() < 1e-3}
+    }
+
+    const LEFT_UP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        up: true,
+        left: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    fn play_until_game_over(game: &mut Game, keys: &engine::MoveKeys) -> f64 {
+        let mut time = 0.0;
+        while game.mode != GameMode::GameOver {
+            time += 1.0 / 60.0;
+            game.run(time, keys);
+        }
+        time
+    }
+
+    fn replay_run(recording: &replay::Recording) -> Game {
+        let mut game = Game::replay(recording, tuning::Tuning::DEFAULT);
+        for frame in recording.frames() {
+            game.run(frame.time, &frame.keys);
+        }
+        game
+    }
+
+    #[test]
+    fn finished_run_is_recorded() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        play_until_game_over(&mut game, &UP_PRESS);
+        let recording = game.take_finished_recording().unwrap();
+        assert_eq! {recording.get_header().seed, SEED}
+        assert! {!recording.frames().is_empty()}
+        assert! {game.take_finished_recording().is_none()}
+    }
+
+    #[test]
+    fn course_run_keeps_its_map_and_is_not_recorded() {
+        let map_file = mapfile::MapFile {
+            seed: SEED,
+            width: MAP_WIDTH,
+            length: MAP_LENGTH,
+            finish_line: MAP_LENGTH,
+            fences: true,
+            obstacles: Vec::new(),
+        };
+        let mut game = Game::from_map_file(CAMERA_DROP, &map_file, "test", TUNING, INIT_TIME);
+        play_until_game_over(&mut game, &LEFT_UP_PRESS);
+        assert_eq! {game.level.get_map_file(), map_file}
+        assert! {game.take_finished_recording().is_none()}
+    }
+
+    #[test]
+    fn replay_reproduces_finish() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        play_until_game_over(&mut game, &UP_PRESS);
+        let recording = game.take_finished_recording().unwrap();
+        let recording = replay::Recording::from_bytes(&recording.to_bytes()).unwrap();
+        assert! {recording.get_header().matches_tuning(&tuning::Tuning::DEFAULT)}
+
+        let replayed = replay_run(&recording);
+        assert_eq! {replayed.mode, GameMode::GameOver}
+        assert_eq! {
+            replayed.level.phoenix.get_position(),
+            game.level.phoenix.get_position()
+        }
+    }
+
+    #[test]
+    fn replay_reproduces_crash() {
+        let mut game = Game::new(
+            CAMERA_DROP,
+            MAP_WIDTH,
+            20.0 * MAP_WIDTH,
+            SEED,
+            TUNING,
+            INIT_TIME,
+        );
+        play_until_game_over(&mut game, &LEFT_UP_PRESS);
+        let recording = game.take_finished_recording().unwrap();
+        let crash_position = game.level.phoenix.get_position();
+        assert! {crash_position.1 < 20.0 * MAP_WIDTH}
+
+        let replayed = replay_run(&recording);
+        assert_eq! {replayed.mode, GameMode::GameOver}
+        assert_eq! {replayed.level.phoenix.get_position(), crash_position}
+    }
+
+    const LEVEL_START: f64 = 0.2;
+    const STEP_DISTANCE: f32 = 200.0 * FRAME_UPDATE_SECONDS as f32;
+
+    fn game_in_level() -> Game {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        game.run(0.1, &UP_PRESS);
+        game.run(LEVEL_START, &NO_PRESS);
+        game
+    }
+
+    fn player_z(game: &Game) -> f32 {
+        game.level.phoenix.get_position().1
+    }
+
+    #[test]
+    fn tuning_applies_to_the_next_run() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        game.set_tuning(tuning::Tuning {
+            player: tuning::PlayerParams {
+                initial_speed: 400.0,
+                ..tuning::PlayerParams::DEFAULT
+            },
+            ..tuning::Tuning::DEFAULT
+        });
+        game.run(0.1, &UP_PRESS);
+        game.run(LEVEL_START, &NO_PRESS);
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 1.10, &NO_PRESS);
+        assert! {(player_z(&game) - (25.0 + 2.0 * STEP_DISTANCE)).abs() < 1e-3}
     }
 
     #[test]
-    fn skip_update_if_delta_t_is_too_short() {
-        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
-        assert_eq! {game.skip_frame((FRAME_UPDATE_SECONDS * 0.90).into()), true}
+    fn new_game_builds_its_map_with_the_given_tuning() {
+        let tuning = tuning::Tuning {
+            map: tuning::MapParams {
+                tile_size: 80.0,
+                ..tuning::MapParams::DEFAULT
+            },
+            ..tuning::Tuning::DEFAULT
+        };
+        let game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, tuning, INIT_TIME);
+        assert_eq! {game.level.get_tuning(), tuning}
     }
 
     #[test]
-    fn do_not_skip_if_time_is_long_enough() {
-        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
-        assert_eq! {game.skip_frame((FRAME_UPDATE_SECONDS * 1.10).into()), false}
+    fn no_step_if_delta_t_is_too_short() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 0.90, &NO_PRESS);
+        assert_eq! {player_z(&game), 25.0}
+    }
+
+    #[test]
+    fn step_if_time_is_long_enough() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 1.10, &NO_PRESS);
+        assert! {(player_z(&game) - (25.0 + STEP_DISTANCE)).abs() < 1e-3}
+    }
+
+    #[test]
+    fn short_frames_accumulate_into_one_step() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 0.45, &NO_PRESS);
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 0.90, &NO_PRESS);
+        assert_eq! {player_z(&game), 25.0}
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 1.35, &NO_PRESS);
+        assert! {(player_z(&game) - (25.0 + STEP_DISTANCE)).abs() < 1e-3}
+    }
+
+    #[test]
+    fn long_hitch_is_capped() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + 0.50, &NO_PRESS);
+        let capped_z = 25.0 + MAX_CATCHUP_STEPS as f32 * STEP_DISTANCE;
+        assert! {(player_z(&game) - capped_z).abs() < 1e-3}
+    }
+
+    #[test]
+    fn simulation_does_not_depend_on_frame_rate() {
+        let run_at_fps = |fps: f64| {
+            let mut game = game_in_level();
+            let n_frames = fps as i32;
+            for frame in 1..=n_frames {
+                let time = LEVEL_START + 0.001 + frame as f64 / fps;
+                game.run(time, &LEFT_UP_PRESS);
+            }
+            game.level.phoenix.get_position()
+        };
+        assert_eq! {run_at_fps(30.0), run_at_fps(144.0)}
     }
 
     #[test]
-    fn skip_update_twice_if_delta_t_is_too_short() {
-        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, INIT_TIME);
-        assert_eq! {game.skip_frame((FRAME_UPDATE_SECONDS * 0.45).into()), true}
-        assert_eq! {game.skip_frame((FRAME_UPDATE_SECONDS * 0.90).into()), true}
-        assert_eq! {game.skip_frame((FRAME_UPDATE_SECONDS * 1.35).into()), false}
+    fn draw_interpolates_between_steps() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 1.50, &NO_PRESS);
+        assert! {(game.get_interpolation_alpha() - 0.50).abs() < 1e-3}
+    }
+
+    #[test]
+    fn ghost_races_after_a_finished_run() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        let time = play_until_game_over(&mut game, &UP_PRESS);
+        choose_after_game_over(&mut game, time, 0);
+        assert_eq! {game.mode, GameMode::Level}
+
+        let frame = recording::install_recorder();
+        game.draw();
+        let frame = frame.borrow();
+        assert! {frame.lines.iter().any(|line| line.params == engine::GHOST_LINE)}
+    }
+
+    const PAUSE_PRESS: engine::MoveKeys = engine::MoveKeys {
+        pause: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const DOWN_PRESS: engine::MoveKeys = engine::MoveKeys {
+        down: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const CONFIRM_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    fn paused_game() -> Game {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + FRAME_UPDATE_SECONDS * 0.50, &PAUSE_PRESS);
+        assert_eq! {game.mode, GameMode::Paused}
+        game
+    }
+
+    const CONFIRM_HOP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        hop: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn resume_with_a_key_that_also_hops_does_not_hop() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + 0.1, &NO_PRESS);
+        game.run(LEVEL_START + 0.2, &PAUSE_PRESS);
+        assert_eq! {game.mode, GameMode::Paused}
+        let mut time = LEVEL_START + 0.2;
+        for _ in 0..10 {
+            time += FRAME_UPDATE_SECONDS;
+            game.run(time, &CONFIRM_HOP_PRESS);
+        }
+        assert_eq! {game.mode, GameMode::Level}
+        assert_eq! {game.level.phoenix.get_altitude(), 0.0}
+        game.run(time + FRAME_UPDATE_SECONDS, &NO_PRESS);
+        game.run(time + 2.0 * FRAME_UPDATE_SECONDS, &CONFIRM_HOP_PRESS);
+        assert! {game.level.phoenix.get_altitude() > 0.0}
+    }
+
+    #[test]
+    fn pause_freezes_level() {
+        let mut game = paused_game();
+        game.run(LEVEL_START + 5.0, &UP_PRESS);
+        game.run(LEVEL_START + 10.0, &NO_PRESS);
+        assert_eq! {player_z(&game), 25.0}
+        assert_eq! {game.level.get_run_time(), 0.0}
+    }
+
+    #[test]
+    fn held_pause_key_does_not_toggle_again() {
+        let mut game = paused_game();
+        game.run(LEVEL_START + 1.0, &PAUSE_PRESS);
+        assert_eq! {game.mode, GameMode::Paused}
+        game.run(LEVEL_START + 1.1, &NO_PRESS);
+        game.run(LEVEL_START + 1.2, &PAUSE_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+        game.run(LEVEL_START + 1.3, &PAUSE_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+    }
+
+    #[test]
+    fn resume_does_not_jump() {
+        let mut game = paused_game();
+        game.run(LEVEL_START + 5.0, &NO_PRESS);
+        game.run(LEVEL_START + 5.1, &PAUSE_PRESS);
+        game.run(LEVEL_START + 5.1 + FRAME_UPDATE_SECONDS * 1.10, &NO_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+        assert! {(player_z(&game) - (25.0 + STEP_DISTANCE)).abs() < 1e-3}
+    }
+
+    #[test]
+    fn restart_from_pause_starts_a_new_run() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + 0.1, &UP_PRESS);
+        game.run(LEVEL_START + 0.2, &PAUSE_PRESS);
+        game.run(LEVEL_START + 0.3, &DOWN_PRESS);
+        game.run(LEVEL_START + 0.4, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+        assert_eq! {player_z(&game), 25.0}
+    }
+
+    #[test]
+    fn quit_from_pause_returns_to_menu() {
+        let mut game = paused_game();
+        game.run(LEVEL_START + 0.1, &DOWN_PRESS);
+        game.run(LEVEL_START + 0.2, &NO_PRESS);
+        game.run(LEVEL_START + 0.3, &DOWN_PRESS);
+        game.run(LEVEL_START + 0.4, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::StartMenu}
+        game.run(LEVEL_START + 0.5, &NO_PRESS);
+        assert_eq! {game.mode, GameMode::StartMenu}
+        assert! {game.take_finished_recording().is_none()}
+    }
+
+    #[test]
+    fn paused_time_is_not_part_of_the_run() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + 0.1, &NO_PRESS);
+        let run_time = game.level.get_run_time();
+        game.run(LEVEL_START + 0.11, &PAUSE_PRESS);
+        game.run(LEVEL_START + 3.0, &NO_PRESS);
+        game.run(LEVEL_START + 3.01, &PAUSE_PRESS);
+        assert! {game.level.get_run_time() - run_time < 2.0 * FRAME_UPDATE_SECONDS}
+    }
+
+    #[test]
+    fn replay_reproduces_pause() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        let mut time = 0.0;
+        for frame in 0..200 {
+            time += 1.0 / 60.0;
+            let keys = if frame == 60 || frame == 120 {
+                PAUSE_PRESS
+            } else {
+                UP_PRESS
+            };
+            game.run(time, &keys);
+        }
+        play_until_game_over(&mut game, &UP_PRESS);
+        let recording = game.take_finished_recording().unwrap();
+        let replayed = replay_run(&recording);
+        assert_eq! {replayed.level.phoenix.get_position(), game.level.phoenix.get_position()}
+    }
+
+    fn choose_after_game_over(game: &mut Game, time: f64, n_down: u32) -> f64 {
+        let mut time = time + GAMEOVER_TIME_SECONDS;
+        for _ in 0..n_down {
+            time += 0.1;
+            game.run(time, &DOWN_PRESS);
+            time += 0.1;
+            game.run(time, &NO_PRESS);
+        }
+        game.run(time + 0.1, &CONFIRM_PRESS);
+        time + 0.1
+    }
+
+    fn draw_game(game: &Game) -> std::rc::Rc<std::cell::RefCell<recording::RecordedFrame>> {
+        let frame = recording::install_recorder();
+        game.draw();
+        frame
+    }
+
+    #[test]
+    fn finish_shows_summary_with_record() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        play_until_game_over(&mut game, &UP_PRESS);
+        let frame = draw_game(&game);
+        let frame = frame.borrow();
+        assert! {frame.has_text("Finished!")}
+        assert! {frame.has_text("distance: 100 / 100")}
+        assert! {frame.has_text("new record!")}
+    }
+
+    #[test]
+    fn crash_shows_summary() {
+        let mut game = Game::new(CAMERA_DROP, 100.0, 100_000.0, SEED, TUNING, INIT_TIME);
+        play_until_game_over(&mut game, &LEFT_UP_PRESS);
+        let frame = draw_game(&game);
+        let frame = frame.borrow();
+        assert! {frame.has_text("Crashed")}
+        assert! {frame.has_text("top speed:")}
+    }
+
+    #[test]
+    fn crash_shakes_the_camera_while_game_over_shows() {
+        let mut game = Game::new(CAMERA_DROP, 100.0, 100_000.0, SEED, TUNING, INIT_TIME);
+        let mut time = play_until_game_over(&mut game, &LEFT_UP_PRESS);
+        let crash_x = game.level.phoenix.get_position().0;
+        let mut offsets = Vec::new();
+        for _ in 0..30 {
+            time += 1.0 / 200.0;
+            game.run(time, &NO_PRESS);
+            offsets.push(game.level.projection.offset_x);
+        }
+        // Following alone would only ever move one way
+        let n_turns = offsets
+            .windows(3)
+            .filter(|x| (x[1] - x[0]) * (x[2] - x[1]) < 0.0)
+            .count();
+        assert! {n_turns >= 2}
+        time += 3.0;
+        game.run(time, &NO_PRESS);
+        assert_eq! {game.level.phoenix.get_position().0, crash_x}
+        let settled = game.level.projection.offset_x;
+        game.run(time + 1.0 / 60.0, &NO_PRESS);
+        assert! {(game.level.projection.offset_x - settled).abs() < 0.01}
+    }
+
+    #[test]
+    fn game_over_waits_before_accepting_choices() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        let time = play_until_game_over(&mut game, &UP_PRESS);
+        game.run(time + 0.1, &CONFIRM_PRESS);
+        game.run(time + 5.0, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::GameOver}
+    }
+
+    #[test]
+    fn new_map_changes_seed() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        let time = play_until_game_over(&mut game, &UP_PRESS);
+        choose_after_game_over(&mut game, time, 1);
+        assert_eq! {game.mode, GameMode::Level}
+        assert_ne! {game.level.get_seed(), SEED}
+        assert_eq! {game.menu.get_seed(), game.level.get_seed()}
+    }
+
+    #[test]
+    fn menu_choice_returns_to_start_menu() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        let time = play_until_game_over(&mut game, &UP_PRESS);
+        let time = choose_after_game_over(&mut game, time, 2);
+        assert_eq! {game.mode, GameMode::StartMenu}
+        game.run(time + 0.1, &NO_PRESS);
+        assert_eq! {game.mode, GameMode::StartMenu}
+    }
+
+    #[test]
+    fn hud_is_drawn_during_run_only() {
+        let mut game = game_in_level();
+        assert! {draw_game(&game).borrow().has_text("to finish:")}
+        game.run(LEVEL_START + 0.1, &PAUSE_PRESS);
+        assert! {draw_game(&game).borrow().has_text("to finish:")}
+        let menu_game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        assert! {!draw_game(&menu_game).borrow().has_text("to finish:")}
+    }
+
+    const RESTART_PRESS: engine::MoveKeys = engine::MoveKeys {
+        restart: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn restart_key_starts_a_new_run() {
+        let mut game = game_in_level();
+        game.run(LEVEL_START + 0.5, &UP_PRESS);
+        assert! {player_z(&game) > 25.0}
+        game.run(LEVEL_START + 0.6, &RESTART_PRESS);
+        game.run(LEVEL_START + 0.61, &RESTART_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+        assert_eq! {player_z(&game), 25.0}
+    }
+
+    #[test]
+    fn replay_after_held_restart_matches() {
+        let mut game = Game::new(
+            CAMERA_DROP,
+            MAP_WIDTH,
+            20.0 * MAP_WIDTH,
+            SEED,
+            TUNING,
+            INIT_TIME,
+        );
+        game.run(0.1, &UP_PRESS);
+        let mut time = LEVEL_START;
+        for frame in 0..60 {
+            time += 1.0 / 60.0;
+            let keys = match frame {
+                30..=39 => RESTART_PRESS,
+                40..=49 => LEFT_UP_PRESS,
+                _ => UP_PRESS,
+            };
+            game.run(time, &keys);
+        }
+        while game.mode != GameMode::GameOver {
+            time += 1.0 / 60.0;
+            game.run(time, &UP_PRESS);
+        }
+        let recording = game.take_finished_recording().unwrap();
+        let replayed = replay_run(&recording);
+        assert_eq! {replayed.level.phoenix.get_position(), game.level.phoenix.get_position()}
+    }
+
+    #[test]
+    fn bindings_screen_opens_from_start_menu() {
+        let mut game = Game::new(CAMERA_DROP, MAP_WIDTH, MAP_LENGTH, SEED, TUNING, INIT_TIME);
+        game.run(0.1, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::Bindings}
+        assert! {draw_game(&game).borrow().has_text("> accelerate: W|Up")}
+
+        game.run(0.2, &NO_PRESS);
+        game.run(0.3, &CONFIRM_PRESS);
+        game.key_pressed(engine::KeyCode::I);
+        game.run(0.4, &NO_PRESS);
+        assert_eq! {
+            game.get_bindings().get_keys(bindings::Action::Accelerate),
+            &[engine::KeyCode::W, engine::KeyCode::Up, engine::KeyCode::I]
+        }
+
+        game.run(0.5, &UP_PRESS);
+        game.run(0.6, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::StartMenu}
+        game.run(0.7, &CONFIRM_PRESS);
+        assert_eq! {game.mode, GameMode::StartMenu}
+    }
+
+    const CAMERA_PRESS: engine::MoveKeys = engine::MoveKeys {
+        camera: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn camera_key_cycles_modes_and_keeps_them_across_runs() {
+        let mut game = game_in_level();
+        assert_eq! {game.level.get_camera_mode(), camera::CameraMode::Chase}
+        game.run(LEVEL_START + 0.05, &CAMERA_PRESS);
+        game.run(LEVEL_START + 0.10, &CAMERA_PRESS);
+        assert_eq! {game.level.get_camera_mode(), camera::CameraMode::Cockpit}
+        game.run(LEVEL_START + 0.15, &NO_PRESS);
+        game.run(LEVEL_START + 0.20, &CAMERA_PRESS);
+        assert_eq! {game.level.get_camera_mode(), camera::CameraMode::TopDown}
+        game.run(LEVEL_START + 0.25, &RESTART_PRESS);
+        assert_eq! {game.mode, GameMode::Level}
+        assert_eq! {game.level.get_camera_mode(), camera::CameraMode::TopDown}
+    }
+
+    const LEFT_PRESS: engine::MoveKeys = engine::MoveKeys {
+        left: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const RIGHT_PRESS: engine::MoveKeys = engine::MoveKeys {
+        right: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const RIGHT_HOP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        right: true,
+        hop: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn hop_at_the_fence_stays_on_the_course() {
+        let map_file = mapfile::MapFile {
+            seed: SEED,
+            width: MAP_WIDTH,
+            length: 20.0 * MAP_WIDTH,
+            finish_line: 20.0 * MAP_WIDTH,
+            fences: true,
+            obstacles: Vec::new(),
+        };
+        let mut game = Game::from_map_file(CAMERA_DROP, &map_file, "test", TUNING, INIT_TIME);
+        game.run(0.1, &UP_PRESS);
+        let mut time = LEVEL_START;
+        let mut max_altitude: f32 = 0.0;
+        let mut held_back = false;
+        let limit = 0.50 * (MAP_WIDTH - crate::PLAYER_WIDTH);
+        for _ in 0..200 {
+            time += FRAME_UPDATE_SECONDS;
+            // Hops just before the fence, so the ship is high above it when it gets there,
+            // then steers back to land clear of it and coasts on
+            let x = game.level.phoenix.get_position().0;
+            let keys = if held_back && game.level.phoenix.get_altitude() > 0.0 {
+                LEFT_PRESS
+            } else if held_back {
+                NO_PRESS
+            } else if x > 0.50 * MAP_WIDTH - 250.0 {
+                RIGHT_HOP_PRESS
+            } else {
+                RIGHT_PRESS
+            };
+            game.run(time, &keys);
+            assert_eq! {game.mode, GameMode::Level}
+            max_altitude = max_altitude.max(game.level.phoenix.get_altitude());
+            let x = game.level.phoenix.get_position().0;
+            assert! {x < limit}
+            held_back |= x > limit - 2.0;
+        }
+        assert! {held_back}
+        assert! {max_altitude > tuning::MapParams::DEFAULT.fence_height}
     }
 }
@@ -0,0 +1,145 @@
+use super::choices;
+use super::engine;
+use super::level;
+
+const TITLE_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -140.0,
+    y: 190.0,
+};
+
+const SUMMARY_X: f32 = -100.0;
+const SUMMARY_Y1: f32 = 140.0;
+const LINE_Y_STEP: f32 = -22.0;
+
+const OPTIONS_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: SUMMARY_X,
+    y: SUMMARY_Y1 + 5.0 * LINE_Y_STEP,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameOverChoice {
+    Retry,
+    NewMap,
+    Menu,
+}
+
+const SEEDED_MAP_CHOICES: [(GameOverChoice, &str); 3] = [
+    (GameOverChoice::Retry, "Retry"),
+    (GameOverChoice::NewMap, "New map"),
+    (GameOverChoice::Menu, "Menu"),
+];
+
+// A hand-authored course has no other map to move on to
+const COURSE_CHOICES: [(GameOverChoice, &str); 2] = [
+    (GameOverChoice::Retry, "Retry"),
+    (GameOverChoice::Menu, "Menu"),
+];
+
+pub struct GameOverScreen {
+    summary: level::RunSummary,
+    choices: choices::ChoiceList<GameOverChoice>,
+}
+
+impl GameOverScreen {
+    pub fn new(
+        summary: level::RunSummary,
+        allow_new_map: bool,
+        active_keys: &engine::MoveKeys,
+    ) -> GameOverScreen {
+        let choices: &'static [(GameOverChoice, &str)] = if allow_new_map {
+            &SEEDED_MAP_CHOICES
+        } else {
+            &COURSE_CHOICES
+        };
+        GameOverScreen {
+            summary,
+            choices: choices::ChoiceList::new(choices, active_keys),
+        }
+    }
+    pub fn update(&mut self, active_keys: &engine::MoveKeys) -> Option<GameOverChoice> {
+        self.choices.update(active_keys)
+    }
+    pub fn draw(&self) {
+        let title = match self.summary.outcome {
+            level::RunOutcome::Crash => "Crashed",
+            level::RunOutcome::Finish => "Finished!",
+        };
+        engine::draw_text(title, TITLE_ANCHOR, engine::TEXT_TITLE);
+        for (index, line) in self.summary_lines().iter().enumerate() {
+            let anchor = engine::PointScreen {
+                x: SUMMARY_X,
+                y: SUMMARY_Y1 + index as f32 * LINE_Y_STEP,
+            };
+            engine::draw_text(line, anchor, engine::TEXT_DEFAULT);
+        }
+        self.choices.draw(OPTIONS_ANCHOR, LINE_Y_STEP);
+    }
+
+    fn summary_lines(&self) -> Vec<String> {
+        let summary = &self.summary;
+        let mut lines = vec![
+            format! {
+                "distance: {:.0} / {:.0}",
+                summary.distance.min(summary.finish_distance),
+                summary.finish_distance
+            },
+            format! {"time: {:.3} s", summary.time},
+            format! {"top speed: {:.0}", summary.top_speed},
+        ];
+        if summary.is_record {
+            lines.push(String::from("new record!"));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const CRASH: level::RunSummary = level::RunSummary {
+        outcome: level::RunOutcome::Crash,
+        distance: 1234.4,
+        finish_distance: 8000.0,
+        time: 3.25,
+        top_speed: 1500.0,
+        is_record: false,
+    };
+
+    fn draw_screen(
+        screen: &GameOverScreen,
+    ) -> std::rc::Rc<std::cell::RefCell<recording::RecordedFrame>> {
+        let frame = recording::install_recorder();
+        screen.draw();
+        frame
+    }
+
+    #[test]
+    fn crash_summary_is_shown() {
+        let frame = draw_screen(&GameOverScreen::new(CRASH, true, &engine::MoveKeys::NONE));
+        let frame = frame.borrow();
+        assert! {frame.has_text("Crashed")}
+        assert! {frame.has_text("distance: 1234 / 8000")}
+        assert! {frame.has_text("time: 3.250 s")}
+        assert! {frame.has_text("top speed: 1500")}
+        assert! {!frame.has_text("new record!")}
+        assert! {frame.has_text("New map")}
+    }
+
+    #[test]
+    fn finish_record_is_shown() {
+        let finish = level::RunSummary {
+            outcome: level::RunOutcome::Finish,
+            distance: 8012.0,
+            is_record: true,
+            ..CRASH
+        };
+        let frame = draw_screen(&GameOverScreen::new(finish, false, &engine::MoveKeys::NONE));
+        let frame = frame.borrow();
+        assert! {frame.has_text("Finished!")}
+        assert! {frame.has_text("distance: 8000 / 8000")}
+        assert! {frame.has_text("new record!")}
+        assert! {!frame.has_text("New map")}
+    }
+}
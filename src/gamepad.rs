@@ -0,0 +1,169 @@
+use super::engine;
+
+// Small stick and trigger readings are noise from a resting pad
+const DEADZONE: f32 = 0.15;
+
+// One pad's controls, read from whichever backend is available
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+#[cfg_attr(
+    not(all(feature = "gamepad", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+struct PadState {
+    stick_x: f32,
+    left_trigger: f32,
+    right_trigger: f32,
+    dpad_up: bool,
+    dpad_down: bool,
+    dpad_left: bool,
+    dpad_right: bool,
+    south: bool,
+    north: bool,
+    west: bool,
+    start: bool,
+}
+
+// Menus move with the d-pad, the stick and triggers only steer and throttle
+#[cfg_attr(
+    not(all(feature = "gamepad", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+fn to_move_keys(pad: &PadState) -> engine::MoveKeys {
+    engine::MoveKeys {
+        up: pad.dpad_up,
+        down: pad.dpad_down,
+        left: pad.dpad_left,
+        right: pad.dpad_right,
+        pause: pad.start,
+        restart: pad.north,
+        confirm: pad.south,
+        hop: pad.south,
+        camera: pad.west,
+        touch: false,
+        steer_axis: engine::quantize_axis(apply_deadzone(pad.stick_x)),
+        throttle_axis: engine::quantize_axis(
+            apply_deadzone(pad.right_trigger) - apply_deadzone(pad.left_trigger),
+        ),
+    }
+}
+
+// Rescaled so the axis still spans the full range past the deadzone
+#[cfg_attr(
+    not(all(feature = "gamepad", not(target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+fn apply_deadzone(value: f32) -> f32 {
+    if value.abs() < DEADZONE {
+        return 0.0;
+    }
+    value.signum() * (value.abs() - DEADZONE) / (1.0 - DEADZONE)
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+pub struct Gamepads {
+    gilrs: Option<gilrs::Gilrs>,
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(error) => {
+                eprintln!("gamepads are not available: {}", error);
+                None
+            }
+        };
+        Gamepads { gilrs }
+    }
+
+    pub fn get_active_move_keys(&mut self) -> engine::MoveKeys {
+        let Some(gilrs) = &mut self.gilrs else {
+            return engine::MoveKeys::NONE;
+        };
+        // Events have to be drained for gilrs to update the pad states
+        while gilrs.next_event().is_some() {}
+        gilrs
+            .gamepads()
+            .map(|(_, gamepad)| to_move_keys(&read_pad(&gamepad)))
+            .fold(engine::MoveKeys::NONE, |keys, pad_keys| {
+                keys.merge(&pad_keys)
+            })
+    }
+}
+
+#[cfg(all(feature = "gamepad", not(target_arch = "wasm32")))]
+fn read_pad(gamepad: &gilrs::Gamepad) -> PadState {
+    let trigger =
+        |button: gilrs::Button| gamepad.button_data(button).map_or(0.0, |data| data.value());
+    PadState {
+        stick_x: gamepad.value(gilrs::Axis::LeftStickX),
+        left_trigger: trigger(gilrs::Button::LeftTrigger2),
+        right_trigger: trigger(gilrs::Button::RightTrigger2),
+        dpad_up: gamepad.is_pressed(gilrs::Button::DPadUp),
+        dpad_down: gamepad.is_pressed(gilrs::Button::DPadDown),
+        dpad_left: gamepad.is_pressed(gilrs::Button::DPadLeft),
+        dpad_right: gamepad.is_pressed(gilrs::Button::DPadRight),
+        south: gamepad.is_pressed(gilrs::Button::South),
+        north: gamepad.is_pressed(gilrs::Button::North),
+        west: gamepad.is_pressed(gilrs::Button::West),
+        start: gamepad.is_pressed(gilrs::Button::Start),
+    }
+}
+
+// Without the gamepad feature, or on the web build, only the keyboard is read
+#[cfg(not(all(feature = "gamepad", not(target_arch = "wasm32"))))]
+pub struct Gamepads;
+
+#[cfg(not(all(feature = "gamepad", not(target_arch = "wasm32"))))]
+impl Gamepads {
+    pub fn new() -> Gamepads {
+        Gamepads
+    }
+
+    pub fn get_active_move_keys(&mut self) -> engine::MoveKeys {
+        engine::MoveKeys::NONE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resting_pad_is_no_input() {
+        let pad = PadState {
+            stick_x: 0.10,
+            right_trigger: 0.05,
+            ..PadState::default()
+        };
+        assert_eq! {to_move_keys(&pad), engine::MoveKeys::NONE}
+    }
+
+    #[test]
+    fn stick_and_triggers_are_analog() {
+        let pad = PadState {
+            stick_x: -1.0,
+            left_trigger: 0.15 + 0.85 * 0.25,
+            right_trigger: 0.15 + 0.85 * 0.75,
+            ..PadState::default()
+        };
+        let keys = to_move_keys(&pad);
+        assert_eq! {keys.get_steer(), -1.0}
+        assert! {(keys.get_throttle() - 0.50).abs() < 1.0 / engine::AXIS_STEPS}
+        assert! {!keys.left && !keys.up}
+    }
+
+    #[test]
+    fn buttons_drive_menus() {
+        let pad = PadState {
+            dpad_down: true,
+            south: true,
+            start: true,
+            ..PadState::default()
+        };
+        let keys = to_move_keys(&pad);
+        assert! {keys.down && keys.confirm && keys.hop && keys.pause}
+        assert_eq! {keys.get_throttle(), -1.0}
+    }
+}
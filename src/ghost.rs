@@ -0,0 +1,143 @@
+use super::engine;
+use super::player;
+use super::projection;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GhostSample {
+    pub x: f32,
+    pub z: f32,
+    pub roll: f32,
+    pub altitude: f32,
+}
+
+impl GhostSample {
+    fn lerp(&self, other: &GhostSample, t: f32) -> GhostSample {
+        GhostSample {
+            x: self.x + (other.x - self.x) * t,
+            z: self.z + (other.z - self.z) * t,
+            roll: self.roll + (other.roll - self.roll) * t,
+            altitude: self.altitude + (other.altitude - self.altitude) * t,
+        }
+    }
+}
+
+// One sample for the start of the run, then one per simulation step
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trajectory {
+    samples: Vec<GhostSample>,
+}
+
+impl Trajectory {
+    pub fn new() -> Trajectory {
+        Trajectory {
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: GhostSample) {
+        self.samples.push(sample);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    // Position at step `step`, `alpha` of the way from the previous step like the player
+    pub fn sample_at(&self, step: usize, alpha: f32) -> Option<GhostSample> {
+        let current = self.samples.get(step)?;
+        let previous = &self.samples[step.saturating_sub(1)];
+        Some(previous.lerp(current, alpha))
+    }
+
+    // `y` is the float height, hops are added from the samples
+    pub fn draw(&self, projection: &projection::Projection, step: usize, alpha: f32, y: f32) {
+        if let Some(sample) = self.sample_at(step, alpha) {
+            let corners =
+                player::triangle_corners((sample.x, sample.z), y + sample.altitude, sample.roll);
+            player::draw_triangle(corners, projection, engine::GHOST_LINE);
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for sample in &self.samples {
+            text += &format!(
+                "ghost = {}, {}, {}, {}\n",
+                sample.x, sample.z, sample.roll, sample.altitude
+            );
+        }
+        text
+    }
+
+    pub fn parse_sample(value: &str) -> Option<GhostSample> {
+        let mut values = value.split(',').map(|number| number.trim().parse::<f32>());
+        let sample = GhostSample {
+            x: values.next()?.ok()?,
+            z: values.next()?.ok()?,
+            roll: values.next()?.ok()?,
+            // Ghosts saved before hops existed stay on the ground
+            altitude: match values.next() {
+                Some(altitude) => altitude.ok()?,
+                None => 0.0,
+            },
+        };
+        match values.next() {
+            Some(_) => None,
+            None => Some(sample),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    fn straight_run() -> Trajectory {
+        let mut trajectory = Trajectory::new();
+        for step in 0..3 {
+            trajectory.push(GhostSample {
+                x: 0.0,
+                z: 100.0 * step as f32,
+                roll: 0.0,
+                altitude: 10.0 * step as f32,
+            });
+        }
+        trajectory
+    }
+
+    #[test]
+    fn sample_interpolates_between_steps() {
+        let sample = straight_run().sample_at(2, 0.25).unwrap();
+        assert_eq! {sample.z, 125.0}
+        assert_eq! {straight_run().sample_at(0, 0.5).unwrap().z, 0.0}
+    }
+
+    #[test]
+    fn ghost_disappears_after_its_run() {
+        assert_eq! {straight_run().sample_at(3, 0.0), None}
+    }
+
+    #[test]
+    fn samples_round_trip_through_text() {
+        let trajectory = straight_run();
+        let mut parsed = Trajectory::new();
+        for line in trajectory.to_text().lines() {
+            let (_, value) = line.split_once('=').unwrap();
+            parsed.push(Trajectory::parse_sample(value).unwrap());
+        }
+        assert_eq! {parsed, trajectory}
+        assert_eq! {Trajectory::parse_sample("1, 2"), None}
+        assert_eq! {Trajectory::parse_sample("1, 2, 3, 4, 5"), None}
+        assert_eq! {Trajectory::parse_sample("1, 2, 3").unwrap().altitude, 0.0}
+    }
+
+    #[test]
+    fn ghost_is_drawn_translucent() {
+        let frame = recording::install_recorder();
+        straight_run().draw(&projection::Projection::new(200.0), 1, 1.0, -190.0);
+        let frame = frame.borrow();
+        assert_eq! {frame.lines.len(), 3}
+        assert! {frame.lines.iter().all(|line| line.params == engine::GHOST_LINE)}
+    }
+}
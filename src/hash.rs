@@ -0,0 +1,21 @@
+// FNV-1a, stable across builds and platforms unlike DefaultHasher
+pub fn content_hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_hash_matches_fnv1a() {
+        assert_eq! {content_hash(""), 0xcbf29ce484222325}
+        assert_eq! {content_hash("a"), 0xaf63dc4c8601ec8c}
+        assert_eq! {content_hash("foobar"), 0x85944171f73967e8}
+    }
+}
@@ -0,0 +1,154 @@
+use super::engine;
+
+const MARGIN: f32 = 20.0;
+const LINE_HEIGHT: f32 = 20.0;
+// Text lines start below the seed shown by the level
+const FIRST_TEXT_LINE: f32 = 1.0;
+const BAR_MARGIN: f32 = 40.0;
+const MARKER_HALF_HEIGHT: f32 = 6.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct HudState {
+    pub speed: f32,
+    pub speed_pu: f32,
+    pub run_time: f64,
+    pub distance: f32,
+    pub finish_distance: f32,
+    pub best_distance: Option<f32>,
+}
+
+pub fn draw(state: &HudState) {
+    draw_text_line(0, &format! {"time: {:.2} s", state.run_time});
+    draw_text_line(
+        1,
+        &format! {"speed: {:.0} ({:.0}%)", state.speed, 100.0 * state.speed_pu},
+    );
+    let to_finish = (state.finish_distance - state.distance).max(0.0);
+    draw_text_line(2, &format! {"to finish: {:.0}", to_finish});
+    draw_progress_bar(state);
+}
+
+fn draw_text_line(index: usize, message: &str) {
+    let anchor = engine::PointScreen {
+        x: -0.50 * engine::get_screen_width() + MARGIN,
+        y: 0.50 * engine::get_screen_height()
+            - MARGIN
+            - (FIRST_TEXT_LINE + index as f32) * LINE_HEIGHT,
+    };
+    engine::draw_text(message, anchor, engine::TEXT_DEFAULT);
+}
+
+fn draw_progress_bar(state: &HudState) {
+    let x_start = -0.50 * engine::get_screen_width() + BAR_MARGIN;
+    let x_end = 0.50 * engine::get_screen_width() - BAR_MARGIN;
+    let y = -0.50 * engine::get_screen_height() + BAR_MARGIN;
+    let x_at = |distance: f32| {
+        let progress = (distance / state.finish_distance).clamp(0.0, 1.0);
+        x_start + progress * (x_end - x_start)
+    };
+    let x_player = x_at(state.distance);
+    engine::draw_line_personalized(
+        engine::PointScreen { x: x_start, y },
+        engine::PointScreen { x: x_player, y },
+        engine::HUD_LINE,
+    );
+    engine::draw_line_personalized(
+        engine::PointScreen { x: x_player, y },
+        engine::PointScreen { x: x_end, y },
+        engine::GRID_LINE,
+    );
+    draw_marker(x_end, y);
+    if let Some(best_distance) = state.best_distance {
+        draw_marker(x_at(best_distance), y);
+    }
+}
+
+fn draw_marker(x: f32, y: f32) {
+    engine::draw_line_personalized(
+        engine::PointScreen {
+            x,
+            y: y - MARKER_HALF_HEIGHT,
+        },
+        engine::PointScreen {
+            x,
+            y: y + MARKER_HALF_HEIGHT,
+        },
+        engine::HUD_LINE,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const STATE: HudState = HudState {
+        speed: 1100.0,
+        speed_pu: 0.5,
+        run_time: 4.256,
+        distance: 2000.0,
+        finish_distance: 8000.0,
+        best_distance: Some(4000.0),
+    };
+
+    fn draw_hud(state: &HudState) -> std::rc::Rc<std::cell::RefCell<recording::RecordedFrame>> {
+        let frame = recording::install_recorder();
+        draw(state);
+        frame
+    }
+
+    fn vertical_marker_xs(frame: &recording::RecordedFrame) -> Vec<f32> {
+        frame
+            .lines
+            .iter()
+            .filter(|line| line.p1.x == line.p2.x)
+            .map(|line| line.p1.x)
+            .collect()
+    }
+
+    #[test]
+    fn shows_time_speed_and_distance() {
+        let frame = draw_hud(&STATE);
+        let frame = frame.borrow();
+        assert! {frame.has_text("time: 4.26 s")}
+        assert! {frame.has_text("speed: 1100 (50%)")}
+        assert! {frame.has_text("to finish: 6000")}
+    }
+
+    #[test]
+    fn progress_bar_fills_up_to_player() {
+        let frame = draw_hud(&STATE);
+        let frame = frame.borrow();
+        let filled = frame
+            .lines
+            .iter()
+            .find(|line| line.params == engine::HUD_LINE && line.p1.y == line.p2.y)
+            .unwrap();
+        // 720 px of bar in an 800 px screen, a quarter of the way
+        assert_eq! {filled.p2.x - filled.p1.x, 180.0}
+    }
+
+    #[test]
+    fn best_distance_has_a_marker() {
+        let with_best = vertical_marker_xs(&draw_hud(&STATE).borrow());
+        assert_eq! {with_best, vec![760.0, 400.0]}
+        let no_best = HudState {
+            best_distance: None,
+            ..STATE
+        };
+        let without_best = vertical_marker_xs(&draw_hud(&no_best).borrow());
+        assert_eq! {without_best, vec![760.0]}
+    }
+
+    #[test]
+    fn progress_is_clamped_past_finish() {
+        let finished = HudState {
+            distance: 8050.0,
+            ..STATE
+        };
+        let frame = draw_hud(&finished);
+        let frame = frame.borrow();
+        assert! {frame.has_text("to finish: 0")}
+        assert! {frame.lines.iter().all(|line| line.p2.x <= 760.0)}
+    }
+}
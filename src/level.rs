@@ -1,55 +1,257 @@
+use super::camera;
 use super::engine;
+use super::ghost;
+use super::hud;
 use super::map;
+use super::mapfile;
 use super::player;
 use super::projection;
+use super::tuning;
 
 pub struct Level {
     game_map: map::Map,
     pub phoenix: player::Player,
     pub projection: projection::Projection,
+    camera: camera::CameraRig,
+    crash: Option<map::Collision>,
+    trajectory: ghost::Trajectory,
     last_reset_timeframe: f64,
+    run_time: f64,
+    top_speed: f32,
     camera_height: f32,
+    map_width: f32,
+    map_length: f32,
+    tuning: tuning::Tuning,
 }
 
-const NO_PRESS: engine::MoveKeys = engine::MoveKeys {
-    up: false,
-    down: false,
-    left: false,
-    right: false,
-};
+const SEED_TEXT_MARGIN: f32 = 20.0;
+// Gap kept to the fences when a hop is held back, touching one counts as a crash
+const FENCE_CLEARANCE: f32 = 1.0;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RunOutcome {
+    Crash,
+    Finish,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RunSummary {
+    pub outcome: RunOutcome,
+    pub distance: f32,
+    pub finish_distance: f32,
+    pub time: f64,
+    pub top_speed: f32,
+    pub is_record: bool,
+}
 
 impl Level {
-    pub fn new(camera_height: f32, map_width: f32, map_length: f32, time: f64) -> Level {
+    pub fn new(
+        camera_height: f32,
+        map_width: f32,
+        map_length: f32,
+        seed: u64,
+        tuning: tuning::Tuning,
+        time: f64,
+    ) -> Level {
+        let game_map = map::Map::new(camera_height, map_width, map_length, seed, tuning.map);
+        Level::from_map(camera_height, game_map, tuning, time)
+    }
+    pub fn from_map(
+        camera_height: f32,
+        game_map: map::Map,
+        tuning: tuning::Tuning,
+        time: f64,
+    ) -> Level {
+        let map_width = game_map.get_width();
+        let map_length = game_map.get_length();
+        // The map keeps the params it was built with
+        let tuning = tuning::Tuning {
+            map: game_map.get_params(),
+            ..tuning
+        };
+        let phoenix = player::Player::new(-camera_height, 0.0, tuning.player);
         let mut ret = Level {
-            game_map: map::Map::new(camera_height, map_width, map_length),
-            phoenix: player::Player::new(-camera_height, time),
+            game_map,
+            camera: camera::CameraRig::new(camera_target(&phoenix), tuning.camera),
+            phoenix,
             projection: projection::Projection::new(camera_height),
+            crash: None,
+            trajectory: ghost::Trajectory::new(),
             last_reset_timeframe: time,
+            run_time: 0.0,
+            top_speed: 0.0,
             camera_height,
+            map_width,
+            map_length,
+            tuning,
         };
-        ret.update(time, &NO_PRESS);
+        ret.update(time, &engine::MoveKeys::NONE);
         ret
     }
     pub fn update(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
-        self.phoenix
-            .update(current_time - self.last_reset_timeframe, active_keys);
-        let player_pos = self.phoenix.get_position();
-        self.projection.set_offset(player_pos.0, player_pos.1);
-        let player_speed = self.phoenix.get_speed_pu();
-        self.projection.set_fov(player_speed);
+        let previous_run_time = self.run_time;
+        self.run_time = current_time - self.last_reset_timeframe;
+        self.phoenix.update(self.run_time, active_keys);
+        self.crash = self.game_map.check_collision(
+            &self.phoenix.get_previous_bounds(),
+            &self.phoenix.get_bounds(),
+        );
+        if let Some(crash) = self.crash {
+            self.phoenix.move_to_impact(crash.time_of_impact);
+        } else {
+            // A hop can clear the fences, the course ends at them all the same
+            self.phoenix
+                .clamp_x(0.50 * (self.map_width - super::PLAYER_WIDTH) - FENCE_CLEARANCE);
+        }
+        self.record_trajectory();
+        self.top_speed = self.top_speed.max(self.phoenix.get_speed());
+        let delta_t = (self.run_time - previous_run_time) as f32;
+        let mut target = camera_target(&self.phoenix);
+        if self.crash.is_some() {
+            // The ship has stopped, there is no motion left to look ahead of
+            target.lateral_speed = 0.0;
+            self.camera.shake();
+        }
+        self.camera.update(target, delta_t);
+        self.camera.apply(&mut self.projection, 1.0);
+    }
+    // Moves the camera on while the simulation is stopped after the run
+    pub fn update_camera(&mut self, delta_t: f32) {
+        self.camera.settle(delta_t);
+        self.camera.apply(&mut self.projection, 1.0);
+    }
+    pub fn cycle_camera_mode(&mut self) {
+        self.camera.set_mode(self.camera.get_mode().next());
+        self.camera.apply(&mut self.projection, 1.0);
+    }
+    pub fn draw(&self, alpha: f32) {
+        let phoenix = self.phoenix.interpolate(alpha);
+        let mut projection = self.projection.clone();
+        self.camera.apply(&mut projection, alpha);
+        self.game_map.draw(&projection);
+        if let Some(crash) = &self.crash {
+            self.game_map.draw_crash(&projection, crash);
+        }
+        let step = self.trajectory.len().saturating_sub(1);
+        let float_y = phoenix.get_y() - phoenix.get_altitude();
+        self.game_map.draw_ghost(&projection, step, alpha, float_y);
+        phoenix.draw(&projection);
+        self.draw_seed();
+    }
+    fn record_trajectory(&mut self) {
+        let position = self.phoenix.get_position();
+        self.trajectory.push(ghost::GhostSample {
+            x: position.0,
+            z: position.1,
+            roll: self.phoenix.roll_angle(),
+            altitude: self.phoenix.get_altitude(),
+        });
+    }
+    fn draw_seed(&self) {
+        let message = format! {"seed: {}", self.get_seed()};
+        let anchor = engine::PointScreen {
+            x: -0.50 * engine::get_screen_width() + SEED_TEXT_MARGIN,
+            y: 0.50 * engine::get_screen_height() - SEED_TEXT_MARGIN,
+        };
+        engine::draw_text(&message, anchor, engine::TEXT_DEFAULT);
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.game_map.get_seed()
     }
-    pub fn draw(&self) {
-        self.game_map.draw(&self.projection);
-        self.phoenix.draw(&self.projection);
+    pub fn get_map_layout(&self) -> (f32, f32, f32) {
+        (self.camera_height, self.map_width, self.map_length)
+    }
+    pub fn get_map_file(&self) -> mapfile::MapFile {
+        self.game_map.to_map_file()
+    }
+    pub fn set_seed(&mut self, seed: u64) {
+        self.game_map = map::Map::new(
+            self.camera_height,
+            self.map_width,
+            self.map_length,
+            seed,
+            self.tuning.map,
+        );
+    }
+    pub fn get_tuning(&self) -> tuning::Tuning {
+        self.tuning
+    }
+    // Player changes apply to the current run, map changes rebuild the map
+    pub fn set_tuning(&mut self, tuning: tuning::Tuning) {
+        if tuning.map != self.tuning.map {
+            self.game_map = self.game_map.with_params(tuning.map);
+        }
+        self.phoenix.set_params(tuning.player);
+        self.camera.set_params(tuning.camera);
+        self.tuning = tuning;
     }
     pub fn check_game_over(&self) -> bool {
-        return self.game_map.check_game_over(self.phoenix.get_shape());
+        self.crash.is_some() || self.game_map.check_game_win(self.phoenix.get_shape())
+    }
+    // Logs the run on the map, call once when the level is over
+    pub fn finish_run(&mut self) -> RunSummary {
+        let outcome = match self.crash {
+            Some(_) => RunOutcome::Crash,
+            None => RunOutcome::Finish,
+        };
+        // Simulated time only, pauses do not count
+        let is_record =
+            self.game_map
+                .reset_run(self.run_time, self.phoenix.get_shape(), &self.trajectory);
+        RunSummary {
+            outcome,
+            distance: self.phoenix.get_position().1,
+            finish_distance: self.game_map.get_finish_line(),
+            time: self.run_time,
+            top_speed: self.top_speed,
+            is_record,
+        }
     }
     pub fn reset(&mut self, time: f64) {
-        let delta_t = time - self.last_reset_timeframe;
-        self.game_map.reset_run(delta_t, self.phoenix.get_shape());
-        self.phoenix = player::Player::new(-self.camera_height, time);
+        self.phoenix = player::Player::new(-self.camera_height, 0.0, self.tuning.player);
+        self.phoenix.wait_for_hop_release();
         self.projection = projection::Projection::new(self.camera_height);
+        let mode = self.camera.get_mode();
+        self.camera = camera::CameraRig::new(camera_target(&self.phoenix), self.tuning.camera);
+        self.camera.set_mode(mode);
+        self.camera.apply(&mut self.projection, 1.0);
+        self.crash = None;
+        self.trajectory = ghost::Trajectory::new();
+        self.record_trajectory();
         self.last_reset_timeframe = time;
+        self.run_time = 0.0;
+        self.top_speed = 0.0;
+    }
+    pub fn draw_hud(&self, alpha: f32) {
+        let phoenix = self.phoenix.interpolate(alpha);
+        hud::draw(&hud::HudState {
+            speed: phoenix.get_speed(),
+            speed_pu: phoenix.get_speed_pu(),
+            run_time: self.run_time,
+            distance: phoenix.get_position().1,
+            finish_distance: self.game_map.get_finish_line(),
+            best_distance: self.game_map.get_best_distance(),
+        });
+    }
+    #[cfg(test)]
+    pub fn get_run_time(&self) -> f64 {
+        self.run_time
+    }
+    #[cfg(test)]
+    pub fn get_camera_mode(&self) -> camera::CameraMode {
+        self.camera.get_mode()
+    }
+}
+
+fn camera_target(phoenix: &player::Player) -> camera::CameraTarget {
+    let position = phoenix.get_position();
+    camera::CameraTarget {
+        x: position.0,
+        y: phoenix.get_y(),
+        z: position.1,
+        nose_z: phoenix.get_nose_z(),
+        lateral_speed: phoenix.get_lateral_speed(),
+        speed_pu: phoenix.get_speed_pu(),
+        roll: phoenix.roll_angle(),
     }
 }
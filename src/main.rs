@@ -1,25 +1,146 @@
+mod aabb;
+mod bindings;
+mod bindingsmenu;
+mod camera;
+mod choices;
 mod engine;
 mod game;
+mod gameover;
+mod gamepad;
+mod ghost;
+mod hash;
+mod hud;
 mod level;
 mod map;
+mod mapfile;
 mod obstacle;
+mod pausemenu;
 mod player;
 mod projection;
+mod raster;
+#[cfg(test)]
+mod recording;
 mod rectangle;
+mod renderer;
+mod replay;
+mod snapshot;
+mod spatial;
 mod startmenu;
+mod storage;
+mod table;
+mod touch;
+mod tuning;
 
 const PLAYER_WIDTH: f32 = 25.0;
 
-#[macroquad::main("Phoenix")]
-async fn main() {
-    let camera_drop: f32 = 0.350 * engine::get_screen_height();
-    let map_width: f32 = 2.0 * engine::get_screen_width();
-    let map_length: f32 = 10.0 * engine::get_screen_width();
+fn camera_drop() -> f32 {
+    0.350 * engine::get_screen_height()
+}
+
+fn map_width() -> f32 {
+    2.0 * engine::get_screen_width()
+}
+
+fn map_length() -> f32 {
+    10.0 * engine::get_screen_width()
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if snapshot::run_from_args(&args) {
+        return;
+    }
+    if args.len() == 3 && args[1] == "--replay" {
+        match replay::Recording::load(&args[2]) {
+            Ok(recording) => macroquad::Window::new("Phoenix", play_replay(recording)),
+            Err(error) => eprintln!("could not load replay {}: {}", args[2], error),
+        }
+        return;
+    }
+    if args.len() == 3 && args[1] == "--map" {
+        match mapfile::MapFile::load(&args[2]) {
+            Ok(map_file) => {
+                macroquad::Window::new("Phoenix", run_course(map_file, args[2].clone()))
+            }
+            Err(error) => eprintln!("could not load map {}: {}", args[2], error),
+        }
+        return;
+    }
+    macroquad::Window::new("Phoenix", run());
+}
+
+async fn run() {
+    let seed: u64 = engine::get_start_seed();
 
-    let mut game = game::Game::new(camera_drop, map_width, map_length, engine::get_time());
+    let game = game::Game::new(
+        camera_drop(),
+        map_width(),
+        map_length(),
+        seed,
+        tuning::Tuning::load(),
+        engine::get_time(),
+    );
+    play(game).await
+}
+
+async fn run_course(map_file: mapfile::MapFile, course: String) {
+    let game = game::Game::from_map_file(
+        camera_drop(),
+        &map_file,
+        &course,
+        tuning::Tuning::load(),
+        engine::get_time(),
+    );
+    play(game).await
+}
+
+async fn play(mut game: game::Game) {
+    let mut gamepads = gamepad::Gamepads::new();
+    let mut touch_controls = touch::TouchControls::new();
+    let mut tuning_watcher = tuning::TuningWatcher::new();
+    loop {
+        engine::clear_background();
+        if let Some(tuning) = tuning_watcher.poll() {
+            game.set_tuning(tuning);
+        }
+        if let Some(key) = engine::get_last_key_pressed() {
+            game.key_pressed(key);
+        }
+        let active_keys = engine::get_active_move_keys(game.get_bindings())
+            .merge(&gamepads.get_active_move_keys())
+            .merge(&touch_controls.update(&engine::get_touches()));
+        game.run(engine::get_time(), &active_keys);
+        if let Some(recording) = game.take_finished_recording() {
+            save_recording(&recording);
+        }
+        game.draw();
+        touch_controls.draw();
+        engine::await_next_frame().await
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_recording(recording: &replay::Recording) {
+    if let Err(error) = recording.save(replay::LAST_RUN_FILE) {
+        eprintln!("could not save replay: {}", error);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_recording(_recording: &replay::Recording) {}
+
+async fn play_replay(recording: replay::Recording) {
+    let tuning = tuning::Tuning::load();
+    if !recording.get_header().matches_tuning(&tuning) {
+        eprintln!("replay was recorded with another tuning, it may play back differently");
+    }
+    let mut game = game::Game::replay(&recording, tuning);
+    let mut frames = recording.frames().iter();
     loop {
         engine::clear_background();
-        game.run(engine::get_time(), &engine::get_active_move_keys());
+        if let Some(frame) = frames.next() {
+            game.run(frame.time, &frame.keys);
+        }
         game.draw();
         engine::await_next_frame().await
     }
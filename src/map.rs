@@ -1,7 +1,14 @@
+use super::aabb;
 use super::engine;
+use super::ghost;
+use super::hash;
+use super::mapfile;
 use super::obstacle;
 use super::projection;
 use super::rectangle;
+use super::spatial;
+use super::storage;
+use super::tuning;
 
 // TODO: use MapPoint instead of (f32, f32)
 // TODO: reference to projection as struct member, instead of passing reference around
@@ -25,93 +32,240 @@ impl MapPoint {
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Collision {
+    pub time_of_impact: f32,
+    pub obstacle_index: usize,
+}
+
 pub struct Map {
+    seed: u64,
     camera_height: f32,
     map_width: f32,
     map_length: f32,
     obstacles: Vec<obstacle::Obstacle>,
-    tile_size: f32,
+    obstacle_buckets: spatial::ZBuckets,
+    n_fence_obstacles: usize,
+    params: tuning::MapParams,
+    // Seeded maps are regenerated when the tuning changes, courses keep their obstacles
+    generated: bool,
     finish_line_z: f32,
     best_line: BestLine,
+    record_key: String,
 }
 
 impl Map {
-    const TILE_SIZE_PX: f32 = 100.0;
-    const FENCE_WIDTH_PX: f32 = 40.0;
-    const FENCE_HEIGHT_PX: f32 = 100.0;
-    const OBSTACLE_SIDE_MIN_PX: f32 = 50.0;
-    const OBSTACLE_SIDE_MAX_PX: f32 = 150.0;
-    const OBSTACLE_SIDE_AVG_PX: f32 =
-        (Map::OBSTACLE_SIDE_MIN_PX + Map::OBSTACLE_SIDE_MAX_PX) * 0.50;
-    const AVERAGE_OBSTACLE_AREA: f32 = Map::OBSTACLE_SIDE_AVG_PX * Map::OBSTACLE_SIDE_AVG_PX;
-
-    pub fn new(camera_height: f32, map_width: f32, map_length: f32) -> Map {
-        let tile_size = Map::TILE_SIZE_PX;
-        let mut map = Map {
+    pub fn new(
+        camera_height: f32,
+        map_width: f32,
+        map_length: f32,
+        seed: u64,
+        params: tuning::MapParams,
+    ) -> Map {
+        let mut record_key = format!("records_seed_{}_{}x{}", seed, map_width, map_length);
+        // Retuned maps roll other obstacles, the default tuning keeps the records made before tuning
+        if params != tuning::MapParams::DEFAULT {
+            record_key += &format!("_{:016x}", hash::content_hash(&params.to_text()));
+        }
+        let mut map = Map::empty(
             camera_height,
             map_width,
             map_length,
-            obstacles: Vec::new(),
-            tile_size,
-            finish_line_z: map_length,
-            best_line: BestLine::new(),
-        };
+            seed,
+            record_key,
+            params,
+        );
+        map.generated = true;
         map.add_fences();
         let mut roller = ObstacleRoller::new(&mut map);
-        roller.roll_map(0.10);
+        roller.roll_map(params.obstacle_density);
         map
     }
 
-    pub fn reset_run(&mut self, time: f64, player_shape: &rectangle::Rectangle) {
-        if self.check_game_win(player_shape) {
-            self.best_line.log_endrun_time(time);
+    pub fn from_map_file(
+        camera_height: f32,
+        map_file: &mapfile::MapFile,
+        params: tuning::MapParams,
+    ) -> Map {
+        // Editing a course gives it new records
+        let record_key = format!(
+            "records_map_{:016x}",
+            hash::content_hash(&map_file.to_text())
+        );
+        let mut map = Map::empty(
+            camera_height,
+            map_file.width,
+            map_file.length,
+            map_file.seed,
+            record_key,
+            params,
+        );
+        map.finish_line_z = map_file.finish_line;
+        if map_file.fences {
+            map.add_fences();
+        }
+        for obstacle in &map_file.obstacles {
+            map.add_obstacle(obstacle.clone());
+        }
+        map
+    }
+
+    pub fn to_map_file(&self) -> mapfile::MapFile {
+        mapfile::MapFile {
+            seed: self.seed,
+            width: self.map_width,
+            length: self.map_length,
+            finish_line: self.finish_line_z,
+            fences: self.n_fence_obstacles > 0,
+            obstacles: self.obstacles[self.n_fence_obstacles..].to_vec(),
+        }
+    }
+
+    pub fn get_params(&self) -> tuning::MapParams {
+        self.params
+    }
+
+    pub fn with_params(&self, params: tuning::MapParams) -> Map {
+        if self.generated {
+            Map::new(
+                self.camera_height,
+                self.map_width,
+                self.map_length,
+                self.seed,
+                params,
+            )
+        } else {
+            Map::from_map_file(self.camera_height, &self.to_map_file(), params)
+        }
+    }
+
+    fn empty(
+        camera_height: f32,
+        map_width: f32,
+        map_length: f32,
+        seed: u64,
+        record_key: String,
+        params: tuning::MapParams,
+    ) -> Map {
+        Map {
+            seed,
+            camera_height,
+            map_width,
+            map_length,
+            obstacles: Vec::new(),
+            obstacle_buckets: spatial::ZBuckets::new(params.tile_size, map_length),
+            n_fence_obstacles: 0,
+            params,
+            generated: false,
+            finish_line_z: map_length,
+            best_line: BestLine::load(&record_key),
+            record_key,
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_width(&self) -> f32 {
+        self.map_width
+    }
+
+    pub fn get_length(&self) -> f32 {
+        self.map_length
+    }
+
+    // Returns true when the run set a new record on this map
+    pub fn reset_run(
+        &mut self,
+        time: f64,
+        player_shape: &rectangle::Rectangle,
+        trajectory: &ghost::Trajectory,
+    ) -> bool {
+        let mut is_record = false;
+        if self.check_game_win(player_shape) && self.best_line.log_endrun_time(time) {
+            self.best_line.ghost = trajectory.clone();
+            is_record = true;
         }
         let player_z = player_shape.get_center().1;
-        self.best_line.log_endrun_distance(player_z);
+        is_record |= self.best_line.log_endrun_distance(player_z);
+        storage::save(&self.record_key, &self.best_line.to_text());
+        is_record
+    }
+
+    pub fn get_finish_line(&self) -> f32 {
+        self.finish_line_z
     }
 
-    pub fn check_game_over(&self, player_shape: &rectangle::Rectangle) -> bool {
-        self.check_collision(player_shape) || self.check_game_win(player_shape)
+    pub fn get_best_distance(&self) -> Option<f32> {
+        let best_distance = self.best_line.best_distance_z;
+        (best_distance >= 0.0).then_some(best_distance)
     }
 
-    fn check_game_win(&self, player_shape: &rectangle::Rectangle) -> bool {
+    pub fn check_game_win(&self, player_shape: &rectangle::Rectangle) -> bool {
         player_shape.get_center().1 >= self.finish_line_z
     }
 
     fn add_fences(&mut self) {
-        let x_fence = (self.map_width + Map::FENCE_WIDTH_PX) * 0.50;
+        let x_fence = (self.map_width + self.params.fence_width) * 0.50;
         self.add_fence(x_fence);
         self.add_fence(-x_fence);
+        self.n_fence_obstacles = self.obstacles.len();
     }
 
     fn add_fence(&mut self, x: f32) {
-        let mut z: f32 = self.tile_size * 0.50;
+        let tile_size = self.params.tile_size;
+        let mut z: f32 = tile_size * 0.50;
         while z < self.map_length {
             self.add_obstacle(obstacle::Obstacle::new(
                 (x, z),
-                (Map::FENCE_WIDTH_PX, self.tile_size),
-                Map::FENCE_HEIGHT_PX,
+                (self.params.fence_width, tile_size),
+                self.params.fence_height,
             ));
-            z += self.tile_size;
+            z += tile_size;
         }
     }
 
     pub fn add_obstacle(&mut self, obstacle: obstacle::Obstacle) {
+        self.obstacle_buckets
+            .insert(self.obstacles.len(), obstacle.get_z_range());
         self.obstacles.push(obstacle);
     }
 
-    fn check_collision(&self, player_shape: &rectangle::Rectangle) -> bool {
-        for obstacle in &self.obstacles {
-            if obstacle.check_collision(player_shape) {
-                return true;
+    // Heights in the player bounds are measured from the ground
+    pub fn check_collision(
+        &self,
+        previous_bounds: &aabb::Aabb,
+        player_bounds: &aabb::Aabb,
+    ) -> Option<Collision> {
+        let mut first_hit: Option<Collision> = None;
+        let previous_range = previous_bounds.get_footprint().get_z_range();
+        let current_range = player_bounds.get_footprint().get_z_range();
+        let swept_range = (
+            previous_range.0.min(current_range.0),
+            previous_range.1.max(current_range.1),
+        );
+        for obstacle_index in self.obstacle_buckets.query(swept_range) {
+            let obstacle = &self.obstacles[obstacle_index];
+            if let Some(time_of_impact) = obstacle.sweep_collision(previous_bounds, player_bounds) {
+                let is_first = match &first_hit {
+                    Some(hit) => time_of_impact < hit.time_of_impact,
+                    None => true,
+                };
+                if is_first {
+                    first_hit = Some(Collision {
+                        time_of_impact,
+                        obstacle_index,
+                    });
+                }
             }
         }
-        false
+        first_hit
     }
 
     pub fn check_collision_obst(&self, other: &obstacle::Obstacle) -> bool {
-        for obstacle in &self.obstacles {
-            if obstacle.check_collision_obst(other) {
+        for obstacle_index in self.obstacle_buckets.query(other.get_z_range()) {
+            if self.obstacles[obstacle_index].check_collision_obst(other) {
                 return true;
             }
         }
@@ -120,15 +274,30 @@ impl Map {
 
     pub fn draw(&self, projection: &projection::Projection) {
         self.draw_grid(projection);
-        for obstacle in &self.obstacles {
-            obstacle.draw(projection, -self.camera_height);
+        for obstacle_index in self
+            .obstacle_buckets
+            .query(projection.get_view_zone_z_range())
+        {
+            self.obstacles[obstacle_index].draw(projection, -self.camera_height);
         }
         self.best_line
             .draw(projection, self.map_width, self.camera_height);
     }
 
+    pub fn draw_ghost(&self, projection: &projection::Projection, step: usize, alpha: f32, y: f32) {
+        self.best_line.ghost.draw(projection, step, alpha, y);
+    }
+
+    pub fn draw_crash(&self, projection: &projection::Projection, crash: &Collision) {
+        self.obstacles[crash.obstacle_index].draw_personalized(
+            projection,
+            -self.camera_height,
+            engine::PHOENIX_LINE,
+        );
+    }
+
     fn draw_grid(&self, projection: &projection::Projection) {
-        self.draw_horizontal_grid_lines(self.tile_size, projection);
+        self.draw_horizontal_grid_lines(self.params.tile_size, projection);
     }
 
     fn draw_horizontal_grid_lines(&self, tile_size: f32, projection: &projection::Projection) {
@@ -157,21 +326,19 @@ impl Map {
         let x = 0.50 * self.map_width;
         let left = projection::Point3D::new(x, -self.camera_height, z);
         let right = projection::Point3D::new(-x, -self.camera_height, z);
-        engine::draw_line_personalized(
-            projection.to_screen(&left),
-            projection.to_screen(&right),
-            draw_params,
-        );
+        projection.draw_segment(&left, &right, draw_params);
     }
 }
 
 pub struct ObstacleRoller<'a> {
     map: &'a mut Map,
+    rng: engine::Rng,
 }
 
 impl ObstacleRoller<'_> {
-    pub fn new(map: &mut Map) -> ObstacleRoller {
-        ObstacleRoller { map }
+    pub fn new(map: &mut Map) -> ObstacleRoller<'_> {
+        let rng = engine::Rng::new(map.seed);
+        ObstacleRoller { map, rng }
     }
 
     fn roll_map(&mut self, area_ratio: f32) {
@@ -184,39 +351,44 @@ impl ObstacleRoller<'_> {
     }
 
     fn get_n_tries(&self, area_ratio: f32) -> i32 {
-        (self.map_area() * area_ratio / Map::AVERAGE_OBSTACLE_AREA).round() as i32
+        (self.map_area() * area_ratio / self.map.params.average_obstacle_area()).round() as i32
     }
 
     fn map_area(&self) -> f32 {
         self.map.map_width * self.map.map_length
     }
 
-    fn roll_random_obstacle(&self) -> obstacle::Obstacle {
+    fn roll_random_obstacle(&mut self) -> obstacle::Obstacle {
         let height: f32 = self.random_height();
-        let size: (f32, f32) = ObstacleRoller::random_size();
+        let size: (f32, f32) = self.random_size();
         let center: (f32, f32) = self.random_map_location(size);
         obstacle::Obstacle::new(center, size, height)
     }
 
-    fn random_map_location(&self, size: (f32, f32)) -> (f32, f32) {
+    fn random_map_location(&mut self, size: (f32, f32)) -> (f32, f32) {
         let x_max: f32 = (self.map.map_width - size.0) * 0.50;
         let x_min: f32 = -x_max;
         let z_max: f32 = self.map.map_length - size.1 * 0.50;
         let z_min: f32 = size.1 * 5.00;
-        let x: f32 = engine::gen_range(x_min, x_max);
-        let z: f32 = engine::gen_range(z_min, z_max);
+        let x: f32 = self.rng.gen_range(x_min, x_max);
+        let z: f32 = self.rng.gen_range(z_min, z_max);
         (x, z)
     }
 
-    fn random_height(&self) -> f32 {
+    fn random_height(&mut self) -> f32 {
         let h_min = 100.0;
         let h_max = self.map.camera_height * 2.0;
-        engine::gen_range(h_min, h_max)
+        self.rng.gen_range(h_min, h_max)
     }
 
-    fn random_size() -> (f32, f32) {
-        let x: f32 = engine::gen_range(Map::OBSTACLE_SIDE_MIN_PX, Map::OBSTACLE_SIDE_MAX_PX);
-        let z: f32 = engine::gen_range(Map::OBSTACLE_SIDE_MIN_PX, Map::OBSTACLE_SIDE_MAX_PX);
+    fn random_size(&mut self) -> (f32, f32) {
+        let params = self.map.params;
+        let x: f32 = self
+            .rng
+            .gen_range(params.obstacle_side_min, params.obstacle_side_max);
+        let z: f32 = self
+            .rng
+            .gen_range(params.obstacle_side_min, params.obstacle_side_max);
         (x, z)
     }
 }
@@ -224,6 +396,7 @@ impl ObstacleRoller<'_> {
 pub struct BestLine {
     best_distance_z: f32,
     best_time_seconds: f32,
+    ghost: ghost::Trajectory,
 }
 
 impl BestLine {
@@ -231,19 +404,67 @@ impl BestLine {
         BestLine {
             best_distance_z: -1.0,
             best_time_seconds: -1.0,
+            ghost: ghost::Trajectory::new(),
+        }
+    }
+
+    fn load(record_key: &str) -> BestLine {
+        match storage::load(record_key) {
+            Some(text) => BestLine::from_text(&text),
+            None => BestLine::new(),
+        }
+    }
+
+    fn from_text(text: &str) -> BestLine {
+        let mut best_line = BestLine::new();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "best_distance" => {
+                    if let Ok(value) = value.trim().parse::<f32>() {
+                        best_line.best_distance_z = value;
+                    }
+                }
+                "best_time" => {
+                    if let Ok(value) = value.trim().parse::<f32>() {
+                        best_line.best_time_seconds = value;
+                    }
+                }
+                "ghost" => {
+                    if let Some(sample) = ghost::Trajectory::parse_sample(value) {
+                        best_line.ghost.push(sample);
+                    }
+                }
+                _ => {}
+            }
         }
+        best_line
     }
 
-    fn log_endrun_distance(&mut self, best_dist: f32) {
+    fn to_text(&self) -> String {
+        format!(
+            "best_distance = {}\nbest_time = {}\n{}",
+            self.best_distance_z,
+            self.best_time_seconds,
+            self.ghost.to_text()
+        )
+    }
+
+    fn log_endrun_distance(&mut self, best_dist: f32) -> bool {
+        let is_record = best_dist > self.best_distance_z;
         self.best_distance_z = self.best_distance_z.max(best_dist);
+        is_record
     }
 
-    fn log_endrun_time(&mut self, time_interval: f64) {
-        if self.best_time_seconds < 0.0 {
+    fn log_endrun_time(&mut self, time_interval: f64) -> bool {
+        let is_record =
+            self.best_time_seconds < 0.0 || (time_interval as f32) < self.best_time_seconds;
+        if is_record {
             self.best_time_seconds = time_interval as f32;
-        } else {
-            self.best_time_seconds = (time_interval as f32).min(self.best_time_seconds);
         }
+        is_record
     }
 
     fn draw_pole(
@@ -255,19 +476,15 @@ impl BestLine {
         let pole_height: f32 = 200.0;
         let bot = pole_location.to_3d(0.0, camera_height);
         let top = pole_location.to_3d(pole_height, camera_height);
-        engine::draw_line_personalized(
-            projection.to_screen(&bot),
-            projection.to_screen(&top),
-            engine::HUD_LINE,
-        );
+        projection.draw_segment(&bot, &top, engine::HUD_LINE);
         self.draw_best_line_text(projection, &top);
     }
 
     pub fn draw(&self, projection: &projection::Projection, map_width: f32, camera_height: f32) {
         let z_line = self.best_distance_z;
-        let line_location = (0.0, z_line);
+        let line_x_range = (-0.50 * map_width, 0.50 * map_width);
         let pole_location = MapPoint::new(map_width * 0.50, z_line);
-        if projection.is_point_in_view_zone(&line_location) {
+        if projection.is_area_in_view_zone(line_x_range, (z_line, z_line)) {
             self.draw_horizontal_line(
                 z_line,
                 projection,
@@ -290,11 +507,7 @@ impl BestLine {
         let x = 0.50 * map_width;
         let left = projection::Point3D::new(x, -camera_height, z);
         let right = projection::Point3D::new(-x, -camera_height, z);
-        engine::draw_line_personalized(
-            projection.to_screen(&left),
-            projection.to_screen(&right),
-            draw_params,
-        );
+        projection.draw_segment(&left, &right, draw_params);
     }
 
     fn draw_best_line_text(
@@ -303,7 +516,9 @@ impl BestLine {
         anchor: &projection::Point3D,
     ) {
         let message: &str = &self.get_best_line_message();
-        engine::draw_text(message, projection.to_screen(anchor), engine::TEXT_DEFAULT);
+        if let Some(anchor) = projection.project_point(anchor) {
+            engine::draw_text(message, anchor, engine::TEXT_DEFAULT);
+        }
     }
 
     fn get_best_line_message(&self) -> String {
@@ -314,3 +529,315 @@ impl BestLine {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    const CAMERA_HEIGHT: f32 = 200.0;
+    const MAP_WIDTH: f32 = 1000.0;
+    const MAP_LENGTH: f32 = 5000.0;
+    const PARAMS: tuning::MapParams = tuning::MapParams::DEFAULT;
+
+    #[test]
+    fn same_seed_same_obstacles() {
+        let map1 = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 42, PARAMS);
+        let map2 = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 42, PARAMS);
+        assert_eq! {map1.obstacles, map2.obstacles}
+    }
+
+    #[test]
+    fn different_seed_different_obstacles() {
+        let map1 = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 42, PARAMS);
+        let map2 = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 43, PARAMS);
+        assert_ne! {map1.obstacles, map2.obstacles}
+    }
+
+    #[test]
+    fn denser_tuning_rolls_more_obstacles() {
+        let map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 42, PARAMS);
+        let denser = map.with_params(tuning::MapParams {
+            obstacle_density: 0.20,
+            ..PARAMS
+        });
+        assert! {denser.obstacles.len() > map.obstacles.len()}
+        assert_eq! {denser.get_params().obstacle_density, 0.20}
+    }
+
+    #[test]
+    fn retuned_course_keeps_its_obstacles() {
+        let map_file = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS).to_map_file();
+        let course = Map::from_map_file(CAMERA_HEIGHT, &map_file, PARAMS);
+        let retuned = course.with_params(tuning::MapParams {
+            obstacle_density: 0.20,
+            fence_height: 300.0,
+            ..PARAMS
+        });
+        assert_eq! {retuned.to_map_file(), map_file}
+        assert_eq! {retuned.obstacles[0].get_height(), 300.0}
+    }
+
+    fn empty_map() -> Map {
+        Map::empty(
+            CAMERA_HEIGHT,
+            MAP_WIDTH,
+            MAP_LENGTH,
+            0,
+            "records_test".to_string(),
+            PARAMS,
+        )
+    }
+
+    #[test]
+    fn generated_map_round_trips_through_map_file() {
+        let map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 5, PARAMS);
+        let text = map.to_map_file().to_text();
+        let loaded = Map::from_map_file(
+            CAMERA_HEIGHT,
+            &mapfile::MapFile::parse(&text).unwrap(),
+            PARAMS,
+        );
+        assert_eq! {loaded.obstacles, map.obstacles}
+        assert_eq! {loaded.get_seed(), map.get_seed()}
+    }
+
+    #[test]
+    fn map_file_sets_finish_line_and_fences() {
+        let map_file = mapfile::MapFile {
+            seed: 0,
+            width: MAP_WIDTH,
+            length: MAP_LENGTH,
+            finish_line: 1000.0,
+            fences: false,
+            obstacles: vec![obstacle::Obstacle::new((0.0, 600.0), (100.0, 100.0), 100.0)],
+        };
+        let map = Map::from_map_file(CAMERA_HEIGHT, &map_file, PARAMS);
+        assert_eq! {map.obstacles, map_file.obstacles}
+        assert! {map.check_game_win(&rectangle::Rectangle::new((0.0, 1000.0), (25.0, 10.0)))}
+        assert_eq! {map.to_map_file(), map_file}
+    }
+
+    fn finish_run(map: &mut Map, time: f64, z: f32) {
+        let trajectory = ghost::Trajectory::new();
+        map.reset_run(
+            time,
+            &rectangle::Rectangle::new((0.0, z), (25.0, 10.0)),
+            &trajectory,
+        );
+    }
+
+    fn straight_trajectory(x: f32) -> ghost::Trajectory {
+        let mut trajectory = ghost::Trajectory::new();
+        trajectory.push(ghost::GhostSample {
+            x,
+            z: 25.0,
+            roll: 0.0,
+            altitude: 0.0,
+        });
+        trajectory.push(ghost::GhostSample {
+            x,
+            z: 50.0,
+            roll: 0.3,
+            altitude: 40.0,
+        });
+        trajectory
+    }
+
+    #[test]
+    fn ghost_is_kept_for_fastest_finish_only() {
+        let mut map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS);
+        let finish = rectangle::Rectangle::new((0.0, MAP_LENGTH), (25.0, 10.0));
+        let crash = rectangle::Rectangle::new((0.0, 900.0), (25.0, 10.0));
+        assert! {map.reset_run(10.0, &finish, &straight_trajectory(1.0))}
+        assert! {!map.reset_run(12.0, &finish, &straight_trajectory(2.0))}
+        assert! {!map.reset_run(5.0, &crash, &straight_trajectory(3.0))}
+        assert_eq! {map.best_line.ghost, straight_trajectory(1.0)}
+        let reloaded = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS);
+        assert_eq! {reloaded.best_line.ghost, straight_trajectory(1.0)}
+    }
+
+    #[test]
+    fn records_persist_for_same_seed() {
+        let mut map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS);
+        finish_run(&mut map, 12.5, MAP_LENGTH);
+        finish_run(&mut map, 20.0, 800.0);
+        let reloaded = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS);
+        assert_eq! {reloaded.best_line.best_time_seconds, 12.5}
+        assert_eq! {reloaded.best_line.best_distance_z, MAP_LENGTH}
+    }
+
+    #[test]
+    fn records_are_kept_per_map() {
+        let mut map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS);
+        finish_run(&mut map, 1.0, 800.0);
+        let other_seed = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 4, PARAMS);
+        assert_eq! {other_seed.best_line.best_distance_z, -1.0}
+        let other_map_file = Map::from_map_file(CAMERA_HEIGHT, &map.to_map_file(), PARAMS);
+        assert_eq! {other_map_file.best_line.best_distance_z, -1.0}
+    }
+
+    #[test]
+    fn records_are_kept_per_tuning() {
+        let mut map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 6, PARAMS);
+        finish_run(&mut map, 1.0, 700.0);
+        let denser = map.with_params(tuning::MapParams {
+            obstacle_density: 0.20,
+            ..PARAMS
+        });
+        assert_eq! {denser.best_line.best_distance_z, -1.0}
+        let reloaded = denser.with_params(PARAMS);
+        assert_eq! {reloaded.best_line.best_distance_z, 700.0}
+    }
+
+    #[test]
+    fn map_file_records_persist() {
+        let map_file = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 3, PARAMS).to_map_file();
+        let mut map = Map::from_map_file(CAMERA_HEIGHT, &map_file, PARAMS);
+        finish_run(&mut map, 1.0, 900.0);
+        let reloaded = Map::from_map_file(CAMERA_HEIGHT, &map_file, PARAMS);
+        assert_eq! {reloaded.best_line.best_distance_z, 900.0}
+    }
+
+    #[test]
+    fn corrupt_records_are_ignored() {
+        let best_line = BestLine::from_text("best_distance = far\nbest_time = 3.5\ngarbage");
+        assert_eq! {best_line.best_distance_z, -1.0}
+        assert_eq! {best_line.best_time_seconds, 3.5}
+    }
+
+    #[test]
+    fn indexed_collision_matches_full_scan() {
+        let map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 7, PARAMS);
+        let mut z = 0.0;
+        while z < MAP_LENGTH {
+            let previous = floating((0.0, z));
+            let current = floating((30.0, z + 60.0));
+            let full_scan = map
+                .obstacles
+                .iter()
+                .filter_map(|obstacle| obstacle.sweep_collision(&previous, &current))
+                .reduce(f32::min);
+            let indexed = map
+                .check_collision(&previous, &current)
+                .map(|hit| hit.time_of_impact);
+            assert_eq! {indexed, full_scan}
+            z += 25.0;
+        }
+    }
+
+    #[test]
+    fn very_long_map_is_generated_without_overlaps() {
+        let map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, 100.0 * MAP_LENGTH, 3, PARAMS);
+        let n_fence = 2 * (100.0 * MAP_LENGTH / PARAMS.tile_size) as usize;
+        let rolled = &map.obstacles[n_fence..];
+        for (i, obstacle) in rolled.iter().enumerate() {
+            let first_overlap = map
+                .obstacle_buckets
+                .query(obstacle.get_z_range())
+                .into_iter()
+                .find(|&j| j != n_fence + i && map.obstacles[j].check_collision_obst(obstacle));
+            assert_eq! {first_overlap, None}
+        }
+    }
+
+    // A player sized box at the default float height
+    fn floating(center: (f32, f32)) -> aabb::Aabb {
+        flying(center, 20.0)
+    }
+
+    fn flying(center: (f32, f32), bottom: f32) -> aabb::Aabb {
+        aabb::Aabb::new(
+            rectangle::Rectangle::new(center, (25.0, 10.0)),
+            (bottom, bottom + 10.0),
+        )
+    }
+
+    #[test]
+    fn fast_player_does_not_tunnel_through_obstacle() {
+        let mut map = empty_map();
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 520.0), (200.0, 5.0), 100.0));
+        let previous = floating((0.0, 500.0));
+        let current = floating((0.0, 540.0));
+        let crash = map.check_collision(&previous, &current).unwrap();
+        assert_eq! {crash.obstacle_index, 0}
+        assert! {(crash.time_of_impact - 12.5 / 40.0).abs() < 1e-6}
+    }
+
+    #[test]
+    fn collision_reports_first_obstacle_hit() {
+        let mut map = empty_map();
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 530.0), (200.0, 5.0), 100.0));
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 515.0), (200.0, 5.0), 100.0));
+        let previous = floating((0.0, 500.0));
+        let current = floating((0.0, 540.0));
+        assert_eq! {map.check_collision(&previous, &current).unwrap().obstacle_index, 1}
+    }
+
+    #[test]
+    fn no_collision_on_clear_path() {
+        let mut map = empty_map();
+        map.add_obstacle(obstacle::Obstacle::new((300.0, 520.0), (50.0, 50.0), 100.0));
+        let previous = floating((0.0, 500.0));
+        let current = floating((0.0, 540.0));
+        assert! {map.check_collision(&previous, &current).is_none()}
+    }
+
+    #[test]
+    fn player_clears_obstacles_lower_than_itself() {
+        let mut map = empty_map();
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 520.0), (200.0, 5.0), 100.0));
+        let previous = flying((0.0, 500.0), 110.0);
+        let current = flying((0.0, 540.0), 110.0);
+        assert! {map.check_collision(&previous, &current).is_none()}
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 530.0), (200.0, 5.0), 150.0));
+        assert_eq! {map.check_collision(&previous, &current).unwrap().obstacle_index, 1}
+    }
+
+    #[test]
+    fn map_keeps_seed() {
+        let map = Map::new(CAMERA_HEIGHT, MAP_WIDTH, MAP_LENGTH, 7, PARAMS);
+        assert_eq! {map.get_seed(), 7}
+    }
+
+    #[test]
+    fn obstacles_beside_the_view_are_not_drawn() {
+        let frame = recording::install_recorder();
+        let mut map = empty_map();
+        map.add_obstacle(obstacle::Obstacle::new((0.0, 1000.0), (50.0, 50.0), 100.0));
+        map.add_obstacle(obstacle::Obstacle::new(
+            (-3000.0, 1000.0),
+            (50.0, 50.0),
+            100.0,
+        ));
+        map.add_obstacle(obstacle::Obstacle::new(
+            (3000.0, 1000.0),
+            (50.0, 50.0),
+            100.0,
+        ));
+        map.draw(&projection::Projection::new(CAMERA_HEIGHT));
+        let frame = frame.borrow();
+        let n_obstacle_lines = frame
+            .lines
+            .iter()
+            .filter(|line| line.params == engine::DEFAULT_LINE)
+            .count();
+        // Base, top and edges of the obstacle ahead only
+        assert_eq! {n_obstacle_lines, 4 + 4 + 4}
+    }
+
+    #[test]
+    fn best_line_shows_best_distance() {
+        let frame = recording::install_recorder();
+        let mut best_line = BestLine::new();
+        best_line.log_endrun_distance(500.0);
+        best_line.draw(
+            &projection::Projection::new(100.0),
+            MAP_WIDTH,
+            CAMERA_HEIGHT,
+        );
+        let frame = frame.borrow();
+        assert! {frame.has_text("best distance: 500")}
+        assert! {frame.lines.iter().all(|l| l.params == engine::HUD_LINE)}
+    }
+}
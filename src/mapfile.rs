@@ -0,0 +1,236 @@
+use super::obstacle;
+use super::table;
+
+// A small TOML subset:
+//
+// seed = 1
+// width = 1600
+// length = 8000
+// finish_line = 8000
+// fences = true
+//
+// [[obstacle]]
+// center = [120, 900]
+// size = [80, 60]
+// height = 150
+
+const OBSTACLE_TABLE: &str = "[[obstacle]]";
+
+#[derive(Debug)]
+pub enum MapFileError {
+    Io(std::io::Error),
+    Syntax { line: usize, message: String },
+    MissingKey { line: usize, key: &'static str },
+}
+
+impl From<table::TableError> for MapFileError {
+    fn from(error: table::TableError) -> MapFileError {
+        match error {
+            table::TableError::Syntax { line, message } => MapFileError::Syntax { line, message },
+            table::TableError::MissingKey { line, key } => MapFileError::MissingKey { line, key },
+        }
+    }
+}
+
+impl std::fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MapFileError::Io(error) => write!(f, "{}", error),
+            MapFileError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            MapFileError::MissingKey { line, key } => {
+                write!(f, "line {}: missing key '{}'", line, key)
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct MapFile {
+    pub seed: u64,
+    pub width: f32,
+    pub length: f32,
+    pub finish_line: f32,
+    pub fences: bool,
+    pub obstacles: Vec<obstacle::Obstacle>,
+}
+
+impl MapFile {
+    pub fn parse(text: &str) -> Result<MapFile, MapFileError> {
+        let mut header = table::Table::new(1);
+        let mut obstacle_tables: Vec<table::Table> = Vec::new();
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = table::strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == OBSTACLE_TABLE {
+                obstacle_tables.push(table::Table::new(line_number));
+                continue;
+            }
+            let table = obstacle_tables.last_mut().unwrap_or(&mut header);
+            table.insert(line_number, line)?;
+        }
+
+        let length = header.get_f32("length")?;
+        let mut map_file = MapFile {
+            seed: header.get_or("seed", 0)?,
+            width: header.get_f32("width")?,
+            length,
+            finish_line: header.get_or("finish_line", length)?,
+            fences: header.get_or("fences", true)?,
+            obstacles: Vec::new(),
+        };
+        header.check_all_used(&["seed", "width", "length", "finish_line", "fences"])?;
+        header.require_positive("width", map_file.width)?;
+        header.require_positive("length", map_file.length)?;
+        header.require_positive("finish_line", map_file.finish_line)?;
+        header.require(
+            "finish_line",
+            map_file.finish_line <= length,
+            "at most the length",
+        )?;
+        let half_width = 0.50 * map_file.width;
+        for table in &obstacle_tables {
+            let center = table.get_pair("center")?;
+            let size = table.get_pair("size")?;
+            let height = table.get_f32("height")?;
+            table.check_all_used(&["center", "size", "height"])?;
+            table.require(
+                "center",
+                (0.0..=length).contains(&center.1) && center.0.abs() <= half_width,
+                "on the map",
+            )?;
+            table.require_positive("size", size.0)?;
+            table.require_positive("size", size.1)?;
+            table.require_positive("height", height)?;
+            map_file
+                .obstacles
+                .push(obstacle::Obstacle::new(center, size, height));
+        }
+        Ok(map_file)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# Phoenix map\n");
+        text += &format!("seed = {}\n", self.seed);
+        text += &format!("width = {}\n", self.width);
+        text += &format!("length = {}\n", self.length);
+        text += &format!("finish_line = {}\n", self.finish_line);
+        text += &format!("fences = {}\n", self.fences);
+        for obstacle in &self.obstacles {
+            let center = obstacle.get_center();
+            let size = obstacle.get_size();
+            text += &format!("\n{}\n", OBSTACLE_TABLE);
+            text += &format!("center = [{}, {}]\n", center.0, center.1);
+            text += &format!("size = [{}, {}]\n", size.0, size.1);
+            text += &format!("height = {}\n", obstacle.get_height());
+        }
+        text
+    }
+
+    pub fn load(path: &str) -> Result<MapFile, MapFileError> {
+        let text = std::fs::read_to_string(path).map_err(MapFileError::Io)?;
+        MapFile::parse(&text)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), MapFileError> {
+        std::fs::write(path, self.to_text()).map_err(MapFileError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLALOM: &str = include_str!("../maps/slalom.toml");
+
+    #[test]
+    fn parse_hand_written_map() {
+        let text = "width = 1000 # comment\nlength = 5000\n\n[[obstacle]]\ncenter = [-100, 800.5]\nsize = [60, 40]\nheight = 150\n";
+        let map_file = MapFile::parse(text).unwrap();
+        assert_eq! {map_file.seed, 0}
+        assert_eq! {map_file.finish_line, 5000.0}
+        assert! {map_file.fences}
+        assert_eq! {
+            map_file.obstacles,
+            vec![obstacle::Obstacle::new((-100.0, 800.5), (60.0, 40.0), 150.0)]
+        }
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let map_file = MapFile {
+            seed: u64::MAX,
+            width: 1600.0,
+            length: 8000.0,
+            finish_line: 7500.0,
+            fences: false,
+            obstacles: vec![
+                obstacle::Obstacle::new((0.1, 900.0), (51.3, 77.7), 123.456),
+                obstacle::Obstacle::new((-300.0, 2000.0), (150.0, 50.0), 100.0),
+            ],
+        };
+        assert_eq! {MapFile::parse(&map_file.to_text()).unwrap(), map_file}
+    }
+
+    #[test]
+    fn bundled_map_parses() {
+        let map_file = MapFile::parse(SLALOM).unwrap();
+        assert! {!map_file.obstacles.is_empty()}
+    }
+
+    #[test]
+    fn missing_key_is_reported() {
+        let error = MapFile::parse("width = 1000\n\n[[obstacle]]\ncenter = [0, 0]\n").unwrap_err();
+        assert! {matches!(error, MapFileError::MissingKey { key: "length", .. })}
+        let error =
+            MapFile::parse("width = 1\nlength = 2\n[[obstacle]]\ncenter = [0, 0]\nsize = [1, 1]\n")
+                .unwrap_err();
+        assert! {matches!(error, MapFileError::MissingKey { line: 3, key: "height" })}
+    }
+
+    #[test]
+    fn errors_point_at_line() {
+        let error = MapFile::parse("width = 1000\nlength = 5000\nlenght = 3\n").unwrap_err();
+        assert_eq! {error.to_string(), "line 3: unknown key 'lenght'"}
+        let error = MapFile::parse("width = 1000\nlength = five\n").unwrap_err();
+        assert! {matches!(error, MapFileError::Syntax { line: 2, .. })}
+        let error =
+            MapFile::parse("width = 1\nlength = 2\n[[obstacle]]\ncenter = 0, 0\n").unwrap_err();
+        assert! {matches!(error, MapFileError::Syntax { line: 4, .. })}
+    }
+
+    fn obstacle_error(obstacle: &str) -> MapFileError {
+        MapFile::parse(&format!(
+            "width = 1000\nlength = 5000\n[[obstacle]]\n{}",
+            obstacle
+        ))
+        .unwrap_err()
+    }
+
+    #[test]
+    fn values_off_the_map_are_errors() {
+        let error = MapFile::parse("width = 1000\nlength = inf\n").unwrap_err();
+        assert! {matches!(error, MapFileError::Syntax { line: 2, .. })}
+        let error = MapFile::parse("width = -1000\nlength = 5000\n").unwrap_err();
+        assert! {matches!(error, MapFileError::Syntax { line: 1, .. })}
+        let error =
+            MapFile::parse("width = 1000\nlength = 5000\nfinish_line = 6000\n").unwrap_err();
+        assert_eq! {error.to_string(), "line 3: 'finish_line' must be at most the length"}
+        let error = obstacle_error("center = [0, nan]\nsize = [1, 1]\nheight = 1\n");
+        assert_eq! {error.to_string(), "line 4: 'center' must be on the map"}
+        let error = obstacle_error("center = [0, 1e30]\nsize = [1, 1]\nheight = 1\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 4, .. })}
+        let error = obstacle_error("center = [inf, 10]\nsize = [1, 1]\nheight = 1\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 4, .. })}
+        let error = obstacle_error("center = [-501, 10]\nsize = [1, 1]\nheight = 1\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 4, .. })}
+        let error = obstacle_error("center = [0, 10]\nsize = [1, -1]\nheight = 1\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 5, .. })}
+        let error = obstacle_error("center = [0, 10]\nsize = [nan, 1]\nheight = 1\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 5, .. })}
+        let error = obstacle_error("center = [0, 10]\nsize = [1, 1]\nheight = 0\n");
+        assert! {matches!(error, MapFileError::Syntax { line: 6, .. })}
+    }
+}
@@ -1,7 +1,9 @@
+use super::aabb;
 use super::engine;
 use super::projection;
 use super::rectangle;
 
+#[derive(Clone, PartialEq, Debug)]
 pub struct Obstacle {
     base: rectangle::Rectangle,
     height: f32,
@@ -16,6 +18,15 @@ impl Obstacle {
     }
 
     pub fn draw(&self, projection: &projection::Projection, y_level: f32) {
+        self.draw_personalized(projection, y_level, engine::DEFAULT_LINE);
+    }
+
+    pub fn draw_personalized(
+        &self,
+        projection: &projection::Projection,
+        y_level: f32,
+        draw_params: engine::DrawParameters,
+    ) {
         if !self.base.is_rectangle_in_view_range(projection) {
             return;
         }
@@ -23,22 +34,40 @@ impl Obstacle {
         let y_low = y_level;
         let y_high = y_level + self.height;
 
-        self.base.draw(y_low, projection);
-        self.base.draw(y_high, projection);
+        self.base.draw_personalized(y_low, projection, draw_params);
+        self.base.draw_personalized(y_high, projection, draw_params);
 
         let corners_low = self.base.get_corners(y_low);
         let corners_high = self.base.get_corners(y_high);
 
         for i in 0..4 {
-            engine::draw_line(
-                projection.to_screen(&corners_low[i]),
-                projection.to_screen(&corners_high[i]),
-            );
+            projection.draw_segment(&corners_low[i], &corners_high[i], draw_params);
         }
     }
 
-    pub fn check_collision(&self, other: &rectangle::Rectangle) -> bool {
-        self.base.collision(other)
+    pub fn sweep_collision(&self, previous: &aabb::Aabb, current: &aabb::Aabb) -> Option<f32> {
+        current.sweep(previous, &self.get_bounds())
+    }
+
+    // Obstacles stand on the ground
+    fn get_bounds(&self) -> aabb::Aabb {
+        aabb::Aabb::new(self.base.clone(), (0.0, self.height))
+    }
+
+    pub fn get_center(&self) -> (f32, f32) {
+        self.base.get_center()
+    }
+
+    pub fn get_size(&self) -> (f32, f32) {
+        self.base.get_size()
+    }
+
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+
+    pub fn get_z_range(&self) -> (f32, f32) {
+        self.base.get_z_range()
     }
 
     pub fn check_collision_obst(&self, other: &Obstacle) -> bool {
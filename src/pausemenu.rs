@@ -0,0 +1,102 @@
+use super::bindings;
+use super::choices;
+use super::engine;
+
+const TITLE_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -110.0,
+    y: 150.0,
+};
+
+const OPTIONS_Y_STEP: f32 = -25.0;
+
+const OPTIONS_ANCHOR: engine::PointScreen = engine::PointScreen { x: -60.0, y: 100.0 };
+
+const HINT_ANCHOR: engine::PointScreen = engine::PointScreen {
+    x: -160.0,
+    y: OPTIONS_ANCHOR.y + 4.0 * OPTIONS_Y_STEP,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PauseChoice {
+    Resume,
+    Restart,
+    QuitToMenu,
+}
+
+const CHOICES: [(PauseChoice, &str); 3] = [
+    (PauseChoice::Resume, "Resume"),
+    (PauseChoice::Restart, "Restart"),
+    (PauseChoice::QuitToMenu, "Quit to menu"),
+];
+
+pub struct PauseMenu {
+    choices: choices::ChoiceList<PauseChoice>,
+    pause_was_down: bool,
+}
+
+impl PauseMenu {
+    pub fn new(active_keys: &engine::MoveKeys) -> PauseMenu {
+        PauseMenu {
+            choices: choices::ChoiceList::new(&CHOICES, active_keys),
+            pause_was_down: active_keys.pause,
+        }
+    }
+    pub fn update(&mut self, active_keys: &engine::MoveKeys) -> Option<PauseChoice> {
+        let pause_pressed = active_keys.pause && !self.pause_was_down;
+        self.pause_was_down = active_keys.pause;
+        let choice = self.choices.update(active_keys);
+        if pause_pressed {
+            return Some(PauseChoice::Resume);
+        }
+        choice
+    }
+    pub fn draw(&self, bindings: &bindings::Bindings) {
+        engine::draw_text("Paused", TITLE_ANCHOR, engine::TEXT_TITLE);
+        self.choices.draw(OPTIONS_ANCHOR, OPTIONS_Y_STEP);
+        let hint = format! {
+            "[{}|{}]: Select  [{}]: Confirm  [{}]: Resume",
+            bindings.get_primary_label(bindings::Action::Accelerate),
+            bindings.get_primary_label(bindings::Action::Decelerate),
+            bindings.get_primary_label(bindings::Action::Confirm),
+            bindings.get_primary_label(bindings::Action::Pause)
+        };
+        engine::draw_text(&hint, HINT_ANCHOR, engine::TEXT_DEFAULT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
+
+    const DOWN_PRESS: engine::MoveKeys = engine::MoveKeys {
+        down: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const CONFIRM_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const PAUSE_PRESS: engine::MoveKeys = engine::MoveKeys {
+        pause: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn confirm_picks_selected_choice() {
+        let mut menu = PauseMenu::new(&NO_PRESS);
+        menu.update(&DOWN_PRESS);
+        assert_eq! {menu.update(&CONFIRM_PRESS), Some(PauseChoice::Restart)}
+    }
+
+    #[test]
+    fn held_pause_key_does_not_resume() {
+        let mut menu = PauseMenu::new(&PAUSE_PRESS);
+        assert_eq! {menu.update(&PAUSE_PRESS), None}
+        assert_eq! {menu.update(&NO_PRESS), None}
+        assert_eq! {menu.update(&PAUSE_PRESS), Some(PauseChoice::Resume)}
+    }
+}
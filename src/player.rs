@@ -1,89 +1,122 @@
+use super::aabb;
 use super::engine;
 use super::projection;
 use super::rectangle;
+use super::tuning;
 
 const PLAYER_DEPTH: f32 = 10.0;
 const PLAYER_Z: f32 = 25.0;
-const FLOAT_HEIGHT: f32 = 15.0 + 0.50 * super::PLAYER_WIDTH * 0.70;
-const INITAL_FWD_SPEED: f32 = 200.0;
-const FWD_ACELERATION: f32 = 1000.0;
-const MIN_SPEED: f32 = 200.0;
-const MAX_SPEED: f32 = 2000.0;
-const TILT_ANGLE_DEG: f32 = 45.0;
-const TILT_ANGLE_RAD: f32 = std::f32::consts::PI * TILT_ANGLE_DEG / 180.0;
+const PLAYER_HEIGHT: f32 = 10.0;
 
+#[derive(Clone)]
 pub struct Player {
     shape: rectangle::Rectangle,
+    previous_shape: rectangle::Rectangle,
     y: f32,
     last_update_time: f64,
     fwd_speed: f32,
-    roll_position: RollPosition,
-}
-
-enum RollPosition {
-    Level,
-    Left,
-    Right,
+    roll: f32,
+    previous_roll: f32,
+    roll_rate: f32,
+    lateral_speed: f32,
+    // Height above the float height, only hops leave it
+    altitude: f32,
+    previous_altitude: f32,
+    vertical_speed: f32,
+    // A hop key still held from a menu does not hop until it is let go once
+    hop_released: bool,
+    params: tuning::PlayerParams,
 }
 
 impl Player {
-    pub fn new(map_y: f32, time: f64) -> Player {
+    pub fn new(map_y: f32, time: f64, params: tuning::PlayerParams) -> Player {
+        let shape = rectangle::Rectangle::new((0.0, PLAYER_Z), (super::PLAYER_WIDTH, PLAYER_DEPTH));
         Player {
-            shape: rectangle::Rectangle::new((0.0, PLAYER_Z), (super::PLAYER_WIDTH, PLAYER_DEPTH)),
-            y: map_y + FLOAT_HEIGHT,
+            previous_shape: shape.clone(),
+            shape,
+            y: map_y + params.float_height,
             last_update_time: time,
-            fwd_speed: INITAL_FWD_SPEED,
-            roll_position: RollPosition::Level,
+            fwd_speed: params.initial_speed,
+            roll: 0.0,
+            previous_roll: 0.0,
+            roll_rate: 0.0,
+            lateral_speed: 0.0,
+            altitude: 0.0,
+            previous_altitude: 0.0,
+            vertical_speed: 0.0,
+            hop_released: true,
+            params,
         }
     }
 
+    // Takes effect mid-run, the ship keeps its speed and position
+    pub fn set_params(&mut self, params: tuning::PlayerParams) {
+        self.y += params.float_height - self.params.float_height;
+        self.params = params;
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.fwd_speed
+    }
+
     pub fn get_speed_pu(&self) -> f32 {
-        (self.fwd_speed - MIN_SPEED) / (MAX_SPEED - MIN_SPEED)
+        (self.fwd_speed - self.params.min_speed) / (self.params.max_speed - self.params.min_speed)
     }
 
     pub fn get_y(&self) -> f32 {
-        self.y
+        self.y + self.altitude
+    }
+
+    // Tip of the ship, where the cockpit camera sits
+    pub fn get_nose_z(&self) -> f32 {
+        self.get_position().1 + 0.50 * PLAYER_DEPTH
+    }
+
+    pub fn get_lateral_speed(&self) -> f32 {
+        self.lateral_speed
+    }
+
+    pub fn get_altitude(&self) -> f32 {
+        self.altitude
+    }
+
+    // Heights are measured from the ground, like obstacles
+    pub fn get_bounds(&self) -> aabb::Aabb {
+        self.bounds(&self.shape, self.altitude)
+    }
+
+    pub fn get_previous_bounds(&self) -> aabb::Aabb {
+        self.bounds(&self.previous_shape, self.previous_altitude)
     }
 
+    fn bounds(&self, shape: &rectangle::Rectangle, altitude: f32) -> aabb::Aabb {
+        let elevation = self.params.float_height + altitude;
+        aabb::Aabb::new(
+            shape.clone(),
+            (
+                elevation - 0.50 * PLAYER_HEIGHT,
+                elevation + 0.50 * PLAYER_HEIGHT,
+            ),
+        )
+    }
+
+    // Drawn from the camera like the rest of the level, which trails the ship sideways
     pub fn draw(&self, projection: &projection::Projection) {
-        let compensated_projection = projection::Projection::make_compensated_projection(
-            projection,
-            self.shape.get_center(),
-        );
-        //self.shape.draw(self.get_y(), &compensated_projection);
-        self.draw_body(&compensated_projection);
-        self.draw_shadow(&compensated_projection);
-    }
-
-    fn roll_angle(&self) -> f32 {
-        match self.roll_position {
-            RollPosition::Level => 0.0,
-            RollPosition::Left => -TILT_ANGLE_RAD,
-            RollPosition::Right => TILT_ANGLE_RAD,
-        }
+        self.draw_body(projection);
+        self.draw_shadow(projection);
+    }
+
+    pub fn roll_angle(&self) -> f32 {
+        self.roll
     }
 
     fn get_triangle_corners(&self) -> [projection::Point3D; 3] {
-        let pos = self.get_position();
-        let def = 0.50 * super::PLAYER_WIDTH;
-        let ang = self.roll_angle();
-        let p1 = projection::Point3D::new(pos.0, self.y, pos.1 + 0.50 * PLAYER_DEPTH);
-        let p2 = projection::Point3D::new(
-            pos.0 + 0.50 * super::PLAYER_WIDTH * ang.cos(),
-            self.y - ang.sin() * def,
-            pos.1 - 0.50 * PLAYER_DEPTH,
-        );
-        let p3 = projection::Point3D::new(
-            pos.0 - 0.50 * super::PLAYER_WIDTH * ang.cos(),
-            self.y + ang.sin() * def,
-            pos.1 - 0.50 * PLAYER_DEPTH,
-        );
-        [p1, p2, p3]
+        triangle_corners(self.get_position(), self.get_y(), self.roll_angle())
     }
 
     fn draw_body(&self, projection: &projection::Projection) {
         let corners = self.get_triangle_corners();
-        self.draw_triangle(corners, projection, engine::PHOENIX_LINE);
+        draw_triangle(corners, projection, engine::PHOENIX_LINE);
     }
 
     fn project_shadow(
@@ -99,78 +132,87 @@ impl Player {
 
     fn draw_shadow(&self, projection: &projection::Projection) {
         let corners = self.get_triangle_corners();
-        let shadow_y = self.get_y() - FLOAT_HEIGHT;
+        // Stays on the ground while the ship hops
+        let shadow_y = self.y - self.params.float_height;
         let shadow_corners = self.project_shadow(corners, shadow_y);
-        self.draw_triangle(shadow_corners, projection, engine::DEFAULT_LINE);
+        draw_triangle(shadow_corners, projection, engine::DEFAULT_LINE);
     }
 
-    fn draw_triangle(
-        &self,
-        corners: [projection::Point3D; 3],
-        projection: &projection::Projection,
-        line: engine::DrawParameters,
-    ) {
-        engine::draw_line_personalized(
-            projection.to_screen(&corners[0]),
-            projection.to_screen(&corners[1]),
-            line,
-        );
-        engine::draw_line_personalized(
-            projection.to_screen(&corners[1]),
-            projection.to_screen(&corners[2]),
-            line,
-        );
-        engine::draw_line_personalized(
-            projection.to_screen(&corners[2]),
-            projection.to_screen(&corners[0]),
-            line,
-        );
+    pub fn interpolate(&self, alpha: f32) -> Player {
+        let mut player = self.clone();
+        let previous = self.previous_shape.get_center();
+        let current = self.shape.get_center();
+        player
+            .shape
+            .move_x((previous.0 - current.0) * (1.0 - alpha));
+        player
+            .shape
+            .move_y((previous.1 - current.1) * (1.0 - alpha));
+        player.roll = self.previous_roll + (self.roll - self.previous_roll) * alpha;
+        player.altitude = self.previous_altitude + (self.altitude - self.previous_altitude) * alpha;
+        player
     }
 
     pub fn update(&mut self, current_time: f64, active_keys: &engine::MoveKeys) {
+        self.previous_shape = self.shape.clone();
+        self.previous_roll = self.roll;
+        self.previous_altitude = self.altitude;
         let delta_t = self.time_since_last_update(current_time);
         if delta_t > 0.0 {
             self.update_fwd_speed(active_keys, delta_t);
-            self.update_size_position(active_keys, delta_t);
-            self.update_roll_position(active_keys);
+            self.update_roll(active_keys, delta_t);
+            self.update_size_position(delta_t);
+            self.update_altitude(active_keys, delta_t);
             self.update_forward_position(delta_t);
         }
         self.last_update_time = current_time;
     }
 
     fn update_fwd_speed(&mut self, active_keys: &engine::MoveKeys, delta_t: f32) {
-        let delta_v = delta_t * FWD_ACELERATION;
-        if active_keys.up {
-            self.fwd_speed += delta_v;
-        }
-        if active_keys.down {
-            self.fwd_speed -= delta_v;
-        }
-        if self.fwd_speed < MIN_SPEED {
-            self.fwd_speed = MIN_SPEED;
+        let delta_v = delta_t * self.params.acceleration;
+        self.fwd_speed += active_keys.get_throttle() * delta_v;
+        if self.fwd_speed < self.params.min_speed {
+            self.fwd_speed = self.params.min_speed;
         }
-        if self.fwd_speed > MAX_SPEED {
-            self.fwd_speed = MAX_SPEED;
+        if self.fwd_speed > self.params.max_speed {
+            self.fwd_speed = self.params.max_speed;
         }
     }
 
-    fn update_size_position(&mut self, active_keys: &engine::MoveKeys, delta_t: f32) {
-        let move_dist = delta_t * self.fwd_speed;
-        if active_keys.left && !active_keys.right {
-            self.shape.move_x(-move_dist);
-        }
-        if !active_keys.left && active_keys.right {
-            self.shape.move_x(move_dist);
-        }
+    // Banking pushes the ship sideways, drag slows it down once level again
+    fn update_size_position(&mut self, delta_t: f32) {
+        let params = &self.params;
+        let bank = self.roll.sin() / params.tilt_angle_rad().sin();
+        let lateral_acceleration =
+            bank * params.lateral_acceleration - params.lateral_drag * self.lateral_speed;
+        self.lateral_speed += lateral_acceleration * delta_t;
+        self.lateral_speed = self
+            .lateral_speed
+            .clamp(-params.max_lateral_speed, params.max_lateral_speed);
+        self.shape.move_x(self.lateral_speed * delta_t);
+    }
+
+    fn update_roll(&mut self, active_keys: &engine::MoveKeys, delta_t: f32) {
+        // Pulled towards the bank the steering asks for
+        let target_roll = active_keys.get_steer() * self.params.tilt_angle_rad();
+        let roll_acceleration = self.params.roll_stiffness * (target_roll - self.roll)
+            - self.params.roll_damping * self.roll_rate;
+        self.roll_rate += roll_acceleration * delta_t;
+        self.roll += self.roll_rate * delta_t;
     }
 
-    fn update_roll_position(&mut self, active_keys: &engine::MoveKeys) {
-        self.roll_position = RollPosition::Level;
-        if active_keys.left && !active_keys.right {
-            self.roll_position = RollPosition::Left;
+    // Hops whenever the ship is back at its float height, so holding the key keeps hopping
+    fn update_altitude(&mut self, active_keys: &engine::MoveKeys, delta_t: f32) {
+        self.hop_released |= !active_keys.hop;
+        if self.altitude <= 0.0 && active_keys.hop && self.hop_released {
+            self.vertical_speed = self.params.jump_speed;
         }
-        if !active_keys.left && active_keys.right {
-            self.roll_position = RollPosition::Right;
+        // Exact under constant gravity, so the hop height does not depend on the step
+        self.altitude += (self.vertical_speed - 0.50 * self.params.gravity * delta_t) * delta_t;
+        self.vertical_speed -= self.params.gravity * delta_t;
+        if self.altitude <= 0.0 {
+            self.altitude = 0.0;
+            self.vertical_speed = 0.0;
         }
     }
 
@@ -189,66 +231,105 @@ impl Player {
     pub fn get_shape(&self) -> &rectangle::Rectangle {
         &self.shape
     }
+
+    // For runs started or resumed from a menu, the key that picked the choice may also hop
+    pub fn wait_for_hop_release(&mut self) {
+        self.hop_released = false;
+    }
+
+    // Keeps the center within `limit` of the middle of the course, sideways motion stops there
+    pub fn clamp_x(&mut self, limit: f32) {
+        let x = self.get_position().0;
+        let clamped = x.clamp(-limit, limit);
+        if clamped != x {
+            self.shape.move_x(clamped - x);
+            self.lateral_speed = 0.0;
+        }
+    }
+
+    pub fn move_to_impact(&mut self, time_of_impact: f32) {
+        self.shape = self.previous_shape.lerp(&self.shape, time_of_impact);
+        self.altitude =
+            self.previous_altitude + (self.altitude - self.previous_altitude) * time_of_impact;
+    }
+}
+
+pub fn triangle_corners(position: (f32, f32), y: f32, roll: f32) -> [projection::Point3D; 3] {
+    let def = 0.50 * super::PLAYER_WIDTH;
+    let p1 = projection::Point3D::new(position.0, y, position.1 + 0.50 * PLAYER_DEPTH);
+    let p2 = projection::Point3D::new(
+        position.0 + 0.50 * super::PLAYER_WIDTH * roll.cos(),
+        y - roll.sin() * def,
+        position.1 - 0.50 * PLAYER_DEPTH,
+    );
+    let p3 = projection::Point3D::new(
+        position.0 - 0.50 * super::PLAYER_WIDTH * roll.cos(),
+        y + roll.sin() * def,
+        position.1 - 0.50 * PLAYER_DEPTH,
+    );
+    [p1, p2, p3]
+}
+
+pub fn draw_triangle(
+    corners: [projection::Point3D; 3],
+    projection: &projection::Projection,
+    line: engine::DrawParameters,
+) {
+    for index in 0..corners.len() {
+        let next = corners[(index + 1) % corners.len()];
+        projection.draw_segment(&corners[index], &next, line);
+    }
 }
 
 #[cfg(test)]
+#[allow(clippy::unnecessary_cast)]
 mod tests {
     use super::*;
+    use crate::recording;
 
-    const NO_PRESS: engine::MoveKeys = engine::MoveKeys {
-        up: false,
-        down: false,
-        left: false,
-        right: false,
-    };
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
 
     const LEFT_PRESS: engine::MoveKeys = engine::MoveKeys {
-        up: false,
-        down: false,
         left: true,
-        right: false,
+        ..engine::MoveKeys::NONE
     };
 
     const RIGHT_PRESS: engine::MoveKeys = engine::MoveKeys {
-        up: false,
-        down: false,
-        left: false,
         right: true,
+        ..engine::MoveKeys::NONE
     };
 
     const LEFT_RIGHT_PRESS: engine::MoveKeys = engine::MoveKeys {
-        up: false,
-        down: false,
         left: true,
         right: true,
+        ..engine::MoveKeys::NONE
     };
 
     const UP_PRESS: engine::MoveKeys = engine::MoveKeys {
         up: true,
-        down: false,
-        left: false,
-        right: false,
+        ..engine::MoveKeys::NONE
     };
 
     const NEXT_FRAME: f64 = (1.0 / 50.0) * (1.05 as f64);
     const INIT_TIME: f64 = 0.0;
+    const PARAMS: tuning::PlayerParams = tuning::PlayerParams::DEFAULT;
 
     #[test]
     fn player_floats_above_ground() {
         let map_y_coord = -400.0;
-        let player = Player::new(map_y_coord, INIT_TIME);
-        assert_eq! { player.get_y(), map_y_coord + FLOAT_HEIGHT}
+        let player = Player::new(map_y_coord, INIT_TIME, PARAMS);
+        assert_eq! { player.get_y(), map_y_coord + PARAMS.float_height}
     }
 
     #[test]
     fn player_initial_position() {
-        let player = Player::new(0.0, INIT_TIME);
+        let player = Player::new(0.0, INIT_TIME, PARAMS);
         assert_eq! {player.get_position(), (0.0, PLAYER_Z)}
     }
 
     #[test]
     fn player_moves_left() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &LEFT_PRESS);
         let player_pos = player.get_position();
         assert! {player_pos.0 < 0.0}
@@ -256,7 +337,7 @@ mod tests {
 
     #[test]
     fn player_moves_right() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &RIGHT_PRESS);
         let player_pos = player.get_position();
         assert! {player_pos.0 > 0.0}
@@ -264,15 +345,158 @@ mod tests {
 
     #[test]
     fn player_does_not_move_sideways() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &LEFT_RIGHT_PRESS);
         let player_pos = player.get_position();
         assert! {player_pos.0 == 0.0}
     }
 
+    #[test]
+    fn half_steer_moves_half_as_far() {
+        let half_left = engine::MoveKeys {
+            steer_axis: -0.50,
+            ..engine::MoveKeys::NONE
+        };
+        let mut full = Player::new(0.0, INIT_TIME, PARAMS);
+        full.update(NEXT_FRAME, &LEFT_PRESS);
+        let mut half = Player::new(0.0, INIT_TIME, PARAMS);
+        half.update(NEXT_FRAME, &half_left);
+        let full_x = full.get_position().0;
+        assert! {(half.get_position().0 - 0.50 * full_x).abs() < 0.01 * full_x.abs()}
+        assert! {(half.roll_angle() - 0.50 * full.roll_angle()).abs() < 1e-6}
+    }
+
+    fn run_for(player: &mut Player, start: f64, seconds: f64, keys: &engine::MoveKeys) -> f64 {
+        let mut time = start;
+        while time < start + seconds {
+            time += 1.0 / 50.0;
+            player.update(time, keys);
+        }
+        time
+    }
+
+    #[test]
+    fn roll_eases_into_turn() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &RIGHT_PRESS);
+        assert! {player.roll_angle() > 0.0 && player.roll_angle() < 0.50 * PARAMS.tilt_angle_rad()}
+        run_for(&mut player, NEXT_FRAME, 1.0, &RIGHT_PRESS);
+        assert! {(player.roll_angle() - PARAMS.tilt_angle_rad()).abs() < 0.01}
+    }
+
+    #[test]
+    fn roll_eases_out_of_turn() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        let time = run_for(&mut player, INIT_TIME, 1.0, &LEFT_PRESS);
+        player.update(time + 1.0 / 50.0, &NO_PRESS);
+        assert! {player.roll_angle() < -0.50 * PARAMS.tilt_angle_rad()}
+        run_for(&mut player, time, 1.0, &NO_PRESS);
+        assert! {player.roll_angle().abs() < 0.01}
+    }
+
+    #[test]
+    fn lateral_acceleration_follows_bank() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &RIGHT_PRESS);
+        let bank = player.roll_angle().sin() / PARAMS.tilt_angle_rad().sin();
+        let expected = bank * PARAMS.lateral_acceleration * NEXT_FRAME as f32;
+        assert! {(player.lateral_speed - expected).abs() < 1e-3}
+    }
+
+    #[test]
+    fn player_drifts_left_after_release() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        let time = run_for(&mut player, INIT_TIME, 0.50, &LEFT_PRESS);
+        let released_x = player.get_position().0;
+        player.update(time + 1.0 / 50.0, &NO_PRESS);
+        assert! {player.get_position().0 < released_x}
+    }
+
+    #[test]
+    fn player_drifts_right_after_release() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        let time = run_for(&mut player, INIT_TIME, 0.50, &RIGHT_PRESS);
+        let released_x = player.get_position().0;
+        player.update(time + 1.0 / 50.0, &NO_PRESS);
+        assert! {player.get_position().0 > released_x}
+    }
+
+    #[test]
+    fn drag_stops_drift() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        let time = run_for(&mut player, INIT_TIME, 0.50, &RIGHT_PRESS);
+        run_for(&mut player, time, 3.0, &NO_PRESS);
+        assert! {player.lateral_speed.abs() < 1.0}
+    }
+
+    #[test]
+    fn lateral_speed_is_capped() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        run_for(&mut player, INIT_TIME, 3.0, &LEFT_PRESS);
+        assert_eq! {player.lateral_speed, -PARAMS.max_lateral_speed}
+    }
+
+    #[test]
+    fn lateral_speed_does_not_depend_on_forward_speed() {
+        let right_up = engine::MoveKeys {
+            up: true,
+            ..RIGHT_PRESS
+        };
+        let mut cruising = Player::new(0.0, INIT_TIME, PARAMS);
+        run_for(&mut cruising, INIT_TIME, 1.0, &RIGHT_PRESS);
+        let mut accelerating = Player::new(0.0, INIT_TIME, PARAMS);
+        run_for(&mut accelerating, INIT_TIME, 1.0, &right_up);
+        assert! {accelerating.get_speed() > cruising.get_speed()}
+        assert_eq! {accelerating.get_position().0, cruising.get_position().0}
+    }
+
+    #[test]
+    fn interpolate_roll_between_updates() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &RIGHT_PRESS);
+        let halfway = player.interpolate(0.50).roll_angle();
+        assert! {(halfway - 0.50 * player.roll_angle()).abs() < 1e-6}
+    }
+
+    #[test]
+    fn partial_throttle_accelerates_less() {
+        let half_throttle = engine::MoveKeys {
+            throttle_axis: 0.50,
+            ..engine::MoveKeys::NONE
+        };
+        let mut full = Player::new(0.0, INIT_TIME, PARAMS);
+        full.update(NEXT_FRAME, &UP_PRESS);
+        let mut half = Player::new(0.0, INIT_TIME, PARAMS);
+        half.update(NEXT_FRAME, &half_throttle);
+        let gain = |player: &Player| player.get_speed() - PARAMS.initial_speed;
+        assert! {(gain(&half) - 0.50 * gain(&full)).abs() < 1e-3}
+    }
+
+    #[test]
+    fn tuned_max_speed_caps_acceleration() {
+        let params = tuning::PlayerParams {
+            max_speed: 300.0,
+            ..PARAMS
+        };
+        let mut player = Player::new(0.0, INIT_TIME, params);
+        run_for(&mut player, INIT_TIME, 1.0, &UP_PRESS);
+        assert_eq! {player.get_speed(), 300.0}
+        assert_eq! {player.get_speed_pu(), 1.0}
+    }
+
+    #[test]
+    fn new_float_height_applies_mid_run() {
+        let mut player = Player::new(-100.0, INIT_TIME, PARAMS);
+        player.set_params(tuning::PlayerParams {
+            float_height: 50.0,
+            ..PARAMS
+        });
+        assert_eq! {player.get_y(), -50.0}
+    }
+
     #[test]
     fn player_moves_forward() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &UP_PRESS);
         let player_pos = player.get_position();
         assert! {player_pos.1 > PLAYER_Z}
@@ -280,20 +504,136 @@ mod tests {
 
     #[test]
     fn player_moves_forward_with_inital_speed() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &NO_PRESS);
         let player_pos = player.get_position();
-        let new_z = PLAYER_Z + INITAL_FWD_SPEED * (NEXT_FRAME as f32);
+        let new_z = PLAYER_Z + PARAMS.initial_speed * (NEXT_FRAME as f32);
         assert_eq! {player_pos.1, new_z}
     }
 
     #[test]
     fn player_accelerates_on_up_press() {
-        let mut player = Player::new(0.0, INIT_TIME);
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
         player.update(NEXT_FRAME, &UP_PRESS);
         let player_pos = player.get_position();
-        let new_z = PLAYER_Z + INITAL_FWD_SPEED * (NEXT_FRAME as f32);
+        let new_z = PLAYER_Z + PARAMS.initial_speed * (NEXT_FRAME as f32);
         // new_z is the position if no acceleration was present
         assert! {player_pos.1 > new_z}
     }
+
+    #[test]
+    fn player_draws_body_and_shadow() {
+        let frame = recording::install_recorder();
+        let player = Player::new(-100.0, INIT_TIME, PARAMS);
+        player.draw(&projection::Projection::new(100.0));
+        let frame = frame.borrow();
+        let count_lines = |params: engine::DrawParameters| {
+            frame.lines.iter().filter(|l| l.params == params).count()
+        };
+        assert_eq! {count_lines(engine::PHOENIX_LINE), 3}
+        assert_eq! {count_lines(engine::DEFAULT_LINE), 3}
+    }
+
+    const HOP_PRESS: engine::MoveKeys = engine::MoveKeys {
+        hop: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    fn draw_lines(player: &Player, params: engine::DrawParameters) -> Vec<recording::RecordedLine> {
+        let frame = recording::install_recorder();
+        player.draw(&projection::Projection::new(100.0));
+        let frame = frame.borrow();
+        frame
+            .lines
+            .iter()
+            .filter(|line| line.params == params)
+            .cloned()
+            .collect()
+    }
+
+    #[test]
+    fn hop_rises_and_lands() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &HOP_PRESS);
+        assert! {player.get_altitude() > 0.0}
+        assert_eq! {player.get_y(), PARAMS.float_height + player.get_altitude()}
+        let apex = PARAMS.jump_speed * PARAMS.jump_speed / (2.0 * PARAMS.gravity);
+        let mut highest: f32 = 0.0;
+        let mut time = NEXT_FRAME;
+        while time < 1.0 {
+            time += 1.0 / 50.0;
+            player.update(time, &NO_PRESS);
+            highest = highest.max(player.get_altitude());
+        }
+        assert! {(highest - apex).abs() < 0.05 * apex}
+        assert_eq! {player.get_altitude(), 0.0}
+    }
+
+    #[test]
+    fn held_hop_keeps_hopping() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        let time = run_for(&mut player, INIT_TIME, 1.0, &HOP_PRESS);
+        player.update(time + 1.0 / 50.0, &HOP_PRESS);
+        assert! {player.get_altitude() > 0.0}
+    }
+
+    #[test]
+    fn hop_held_from_a_menu_waits_for_a_release() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.wait_for_hop_release();
+        let time = run_for(&mut player, INIT_TIME, 0.2, &HOP_PRESS);
+        assert_eq! {player.get_altitude(), 0.0}
+        player.update(time + 1.0 / 50.0, &NO_PRESS);
+        player.update(time + 2.0 / 50.0, &HOP_PRESS);
+        assert! {player.get_altitude() > 0.0}
+    }
+
+    #[test]
+    fn bounds_rise_with_the_hop() {
+        let mut player = Player::new(-100.0, INIT_TIME, PARAMS);
+        let floating = player.get_bounds();
+        player.update(NEXT_FRAME, &HOP_PRESS);
+        assert_eq! {player.get_previous_bounds(), floating}
+        assert_ne! {player.get_bounds(), floating}
+    }
+
+    #[test]
+    fn shadow_stays_on_the_ground_during_hop() {
+        let mut player = Player::new(-100.0, INIT_TIME, PARAMS);
+        let mut still = player.clone();
+        player.update(NEXT_FRAME, &HOP_PRESS);
+        still.update(NEXT_FRAME, &NO_PRESS);
+        assert_eq! {
+            draw_lines(&player, engine::DEFAULT_LINE),
+            draw_lines(&still, engine::DEFAULT_LINE)
+        }
+        assert_ne! {
+            draw_lines(&player, engine::PHOENIX_LINE),
+            draw_lines(&still, engine::PHOENIX_LINE)
+        }
+    }
+
+    #[test]
+    fn interpolate_altitude_between_updates() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &HOP_PRESS);
+        let halfway = player.interpolate(0.50).get_altitude();
+        assert! {(halfway - 0.50 * player.get_altitude()).abs() < 1e-4}
+    }
+
+    #[test]
+    fn interpolate_between_updates() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &NO_PRESS);
+        let halfway = player.interpolate(0.50).get_position();
+        let new_z = PLAYER_Z + 0.50 * PARAMS.initial_speed * (NEXT_FRAME as f32);
+        assert! {(halfway.1 - new_z).abs() < 1e-3}
+    }
+
+    #[test]
+    fn interpolate_to_latest_update() {
+        let mut player = Player::new(0.0, INIT_TIME, PARAMS);
+        player.update(NEXT_FRAME, &LEFT_PRESS);
+        assert_eq! {player.interpolate(1.0).get_position(), player.get_position()}
+    }
 }
@@ -1,5 +1,7 @@
 use super::engine;
+use vector3d::Vector3d;
 
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Point3D {
     pub x: f32,
     pub y: f32,
@@ -12,11 +14,36 @@ impl Point3D {
     }
 }
 
+// Where the eye sits and how it sees, camera modes differ only in this and the orientation
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Lens {
+    // The ground is at minus the camera drop
+    pub eye_height: f32,
+    // Distance of the eye behind the offset in FOV distances, so things at the offset keep their size
+    pub eye_behind: f32,
+    // Screen units per world unit of a parallel projection, None for perspective
+    pub orthographic_scale: Option<f32>,
+}
+
+impl Lens {
+    pub const CHASE: Lens = Lens {
+        eye_height: 0.0,
+        eye_behind: 1.0,
+        orthographic_scale: None,
+    };
+}
+
+#[derive(Clone)]
 pub struct Projection {
     fov_distance: f32,
     pub offset_x: f32,
     pub offset_z: f32,
     draw_distance: f32,
+    lens: Lens,
+    // Yaw, pitch and roll
+    orientation: (f32, f32, f32),
+    // Rows turn a point relative to the eye into view space, the camera's right, up and forward
+    view_axes: [Vector3d<f32>; 3],
 }
 
 impl Projection {
@@ -24,6 +51,8 @@ impl Projection {
     const FOV_MIN: f32 = 400.0;
     const HORIZON_DELTA: f32 = 10.0;
     const Z_MAX: f32 = 4000.0;
+    // Closest distance in front of the eye that is drawn, the projection blows up at the eye
+    const NEAR_DISTANCE: f32 = 1.0;
     pub fn new(camera_height: f32) -> Projection {
         let horizon_drop: f32 = Projection::HORIZON_DELTA / camera_height;
         let fov_distance = Projection::Z_MAX / (1.0 + 1.0 / horizon_drop);
@@ -32,15 +61,81 @@ impl Projection {
             offset_x: 0.0,
             offset_z: 0.0,
             draw_distance: Projection::Z_MAX,
+            lens: Lens::CHASE,
+            orientation: (0.0, 0.0, 0.0),
+            view_axes: view_axes(0.0, 0.0, 0.0),
+        }
+    }
+
+    #[cfg(test)]
+    fn to_screen(&self, point: &Point3D) -> engine::PointScreen {
+        self.view_to_screen(self.to_view(point))
+    }
+
+    // Relative to the eye, then turned with the camera
+    fn to_view(&self, point: &Point3D) -> Vector3d<f32> {
+        let relative = Vector3d::new(
+            point.x - self.offset_x,
+            point.y - self.lens.eye_height,
+            point.z - self.offset_z + self.lens.eye_behind * self.fov_distance,
+        );
+        Vector3d::new(
+            self.view_axes[0].dot(relative),
+            self.view_axes[1].dot(relative),
+            self.view_axes[2].dot(relative),
+        )
+    }
+
+    // None for points behind the near plane
+    pub fn project_point(&self, point: &Point3D) -> Option<engine::PointScreen> {
+        let view = self.to_view(point);
+        (view.z >= Projection::NEAR_DISTANCE).then(|| self.view_to_screen(view))
+    }
+
+    // The part of the segment behind the near plane is cut off before projecting
+    pub fn project_segment(
+        &self,
+        start: &Point3D,
+        end: &Point3D,
+    ) -> Option<(engine::PointScreen, engine::PointScreen)> {
+        let start = self.to_view(start);
+        let end = self.to_view(end);
+        if start.z < Projection::NEAR_DISTANCE && end.z < Projection::NEAR_DISTANCE {
+            return None;
         }
+        let clip = |inside: Vector3d<f32>, outside: Vector3d<f32>| {
+            let t = (inside.z - Projection::NEAR_DISTANCE) / (inside.z - outside.z);
+            inside + (outside - inside) * t
+        };
+        let (start, end) = if start.z < Projection::NEAR_DISTANCE {
+            (clip(end, start), end)
+        } else if end.z < Projection::NEAR_DISTANCE {
+            (start, clip(start, end))
+        } else {
+            (start, end)
+        };
+        Some((self.view_to_screen(start), self.view_to_screen(end)))
     }
 
-    pub fn to_screen(&self, point: &Point3D) -> engine::PointScreen {
-        let corrected_z = point.z - self.offset_z;
-        let projection: f32 = self.fov_distance / (corrected_z + self.fov_distance);
+    fn view_to_screen(&self, view: Vector3d<f32>) -> engine::PointScreen {
+        let projection: f32 = match self.lens.orthographic_scale {
+            Some(scale) => scale,
+            None => self.fov_distance / view.z,
+        };
         engine::PointScreen {
-            x: (point.x - self.offset_x) * projection,
-            y: point.y * projection,
+            x: view.x * projection,
+            y: view.y * projection,
+        }
+    }
+
+    pub fn draw_segment(
+        &self,
+        start: &Point3D,
+        end: &Point3D,
+        draw_params: engine::DrawParameters,
+    ) {
+        if let Some((start, end)) = self.project_segment(start, end) {
+            engine::draw_line_personalized(start, end, draw_params);
         }
     }
 
@@ -49,6 +144,22 @@ impl Projection {
         self.offset_z = z;
     }
 
+    pub fn set_lens(&mut self, lens: Lens) {
+        self.lens = lens;
+    }
+
+    // In radians: yaw turns right, pitch tilts down and roll banks like the ship, right side down
+    pub fn set_orientation(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        self.orientation = (yaw, pitch, roll);
+        self.view_axes = view_axes(yaw, pitch, roll);
+    }
+
+    #[cfg(test)]
+    pub fn get_orientation(&self) -> (f32, f32, f32) {
+        self.orientation
+    }
+
+    #[cfg(test)]
     pub fn get_fov(&self) -> f32 {
         self.fov_distance
     }
@@ -58,26 +169,242 @@ impl Projection {
             Projection::FOV_MIN + (fov_pu) * (Projection::FOV_MAX - Projection::FOV_MIN);
     }
 
-    pub fn make_compensated_projection(base: &Projection, offset: (f32, f32)) -> Projection {
-        Projection {
-            fov_distance: base.get_fov(),
-            offset_x: offset.0,
-            offset_z: offset.1,
-            draw_distance: base.draw_distance,
+    // Exact for areas aligned with the axes, since the view zone is widest at their far end
+    pub fn is_area_in_view_zone(&self, x_range: (f32, f32), z_range: (f32, f32)) -> bool {
+        let view_z_range = self.get_view_zone_z_range();
+        if z_range.1 < view_z_range.0 || view_z_range.1 < z_range.0 {
+            return false;
         }
-    }
-
-    pub fn is_point_in_view_zone(&self, point_xz: &(f32, f32)) -> bool {
-        self.is_z_in_range(point_xz.1)
-        // Optional: add x detection
+        let view_x_range = self.get_view_zone_x_range(z_range.1.min(view_z_range.1));
+        (x_range.0 <= view_x_range.1) && (view_x_range.0 <= x_range.1)
     }
 
     pub fn get_view_zone_z_range(&self) -> (f32, f32) {
+        if let Some(reach) = self.get_orthographic_reach() {
+            let eye_z = self.offset_z - self.lens.eye_behind * self.fov_distance;
+            return (eye_z - reach, eye_z + reach);
+        }
         (self.offset_z, self.offset_z + self.draw_distance)
     }
 
-    fn is_z_in_range(&self, z: f32) -> bool {
-        let range = self.get_view_zone_z_range();
-        (range.0 <= z) && (z <= range.1)
+    // What projects onto the screen width at depth `z`, wider the further away.
+    // A rolled screen is covered by its bounding box, turned views are not culled sideways.
+    pub fn get_view_zone_x_range(&self, z: f32) -> (f32, f32) {
+        if let Some(reach) = self.get_orthographic_reach() {
+            return (self.offset_x - reach, self.offset_x + reach);
+        }
+        let (yaw, pitch, roll) = self.orientation;
+        if yaw != 0.0 || pitch != 0.0 {
+            return (f32::NEG_INFINITY, f32::INFINITY);
+        }
+        let screen_width = engine::get_screen_width() * roll.cos().abs()
+            + engine::get_screen_height() * roll.sin().abs();
+        let corrected_z = z - self.offset_z + self.lens.eye_behind * self.fov_distance;
+        let half_width = 0.50 * screen_width * corrected_z / self.fov_distance;
+        (self.offset_x - half_width, self.offset_x + half_width)
+    }
+
+    // Parallel views are taken to look straight down, they see a disc under the eye whatever the roll
+    fn get_orthographic_reach(&self) -> Option<f32> {
+        self.lens.orthographic_scale.map(|scale| {
+            0.50 * engine::get_screen_width().hypot(engine::get_screen_height()) / scale
+        })
+    }
+}
+
+// The camera's right, up and forward axes in world space, for a camera turned by yaw,
+// then pitch, then roll
+fn view_axes(yaw: f32, pitch: f32, roll: f32) -> [Vector3d<f32>; 3] {
+    let (sin_yaw, cos_yaw) = yaw.sin_cos();
+    let (sin_pitch, cos_pitch) = pitch.sin_cos();
+    let (sin_roll, cos_roll) = roll.sin_cos();
+    let turn = |v: Vector3d<f32>| {
+        let rolled = Vector3d::new(
+            v.x * cos_roll + v.y * sin_roll,
+            v.y * cos_roll - v.x * sin_roll,
+            v.z,
+        );
+        let pitched = Vector3d::new(
+            rolled.x,
+            rolled.y * cos_pitch - rolled.z * sin_pitch,
+            rolled.y * sin_pitch + rolled.z * cos_pitch,
+        );
+        Vector3d::new(
+            pitched.x * cos_yaw + pitched.z * sin_yaw,
+            pitched.y,
+            pitched.z * cos_yaw - pitched.x * sin_yaw,
+        )
+    };
+    [
+        turn(Vector3d::new(1.0, 0.0, 0.0)),
+        turn(Vector3d::new(0.0, 1.0, 0.0)),
+        turn(Vector3d::new(0.0, 0.0, 1.0)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    fn projection_at(x: f32, z: f32) -> Projection {
+        recording::install_recorder();
+        let mut projection = Projection::new(200.0);
+        projection.set_offset(x, z);
+        projection
+    }
+
+    #[test]
+    fn view_zone_edges_project_to_screen_edges() {
+        let projection = projection_at(100.0, 500.0);
+        for z in [500.0, 1000.0, 3000.0] {
+            let x_range = projection.get_view_zone_x_range(z);
+            let left = projection.to_screen(&Point3D::new(x_range.0, 0.0, z));
+            let right = projection.to_screen(&Point3D::new(x_range.1, 0.0, z));
+            assert! {(left.x + 400.0).abs() < 1e-2}
+            assert! {(right.x - 400.0).abs() < 1e-2}
+        }
+    }
+
+    #[test]
+    fn area_beside_the_view_is_out() {
+        let projection = projection_at(0.0, 0.0);
+        let edge = projection.get_view_zone_x_range(1000.0).1;
+        assert! {projection.is_area_in_view_zone((edge - 1.0, edge + 50.0), (900.0, 1000.0))}
+        assert! {!projection.is_area_in_view_zone((edge + 1.0, edge + 50.0), (900.0, 1000.0))}
+        assert! {!projection.is_area_in_view_zone((-edge - 50.0, -edge - 1.0), (900.0, 1000.0))}
+    }
+
+    #[test]
+    fn area_behind_or_past_draw_distance_is_out() {
+        let projection = projection_at(0.0, 1000.0);
+        assert! {!projection.is_area_in_view_zone((-10.0, 10.0), (900.0, 990.0))}
+        assert! {!projection.is_area_in_view_zone((-10.0, 10.0), (5010.0, 5100.0))}
+        assert! {projection.is_area_in_view_zone((-10.0, 10.0), (990.0, 1010.0))}
+    }
+
+    #[test]
+    fn area_wider_than_the_view_is_in() {
+        let projection = projection_at(0.0, 0.0);
+        assert! {projection.is_area_in_view_zone((-1.0e5, 1.0e5), (100.0, 200.0))}
+    }
+
+    #[test]
+    fn segment_in_front_is_not_clipped() {
+        let projection = projection_at(0.0, 0.0);
+        let start = Point3D::new(-50.0, -100.0, 10.0);
+        let end = Point3D::new(50.0, -100.0, 800.0);
+        assert_eq! {
+            projection.project_segment(&start, &end),
+            Some((projection.to_screen(&start), projection.to_screen(&end)))
+        }
+    }
+
+    #[test]
+    fn segment_straddling_the_camera_is_cut_at_the_near_plane() {
+        let projection = projection_at(0.0, 1000.0);
+        let eye_z = 1000.0 - projection.get_fov();
+        let ahead = Point3D::new(100.0, -100.0, 1500.0);
+        let behind = Point3D::new(100.0, -100.0, eye_z - 500.0);
+        let (start, end) = projection.project_segment(&behind, &ahead).unwrap();
+        assert_eq! {end, projection.to_screen(&ahead)}
+        let clipped = projection.to_screen(&Point3D::new(
+            100.0,
+            -100.0,
+            eye_z + Projection::NEAR_DISTANCE,
+        ));
+        // Large values so close to the eye, compare relative to their size
+        assert! {(start.x / clipped.x - 1.0).abs() < 1e-3}
+        assert! {(start.y / clipped.y - 1.0).abs() < 1e-3}
+        // Unclipped, the point behind the eye flips to the other side of the screen
+        assert! {projection.to_screen(&behind).x < 0.0 && start.x > 0.0}
+        let (_, reversed_end) = projection.project_segment(&ahead, &behind).unwrap();
+        assert! {(reversed_end.x / start.x - 1.0).abs() < 1e-3}
+    }
+
+    #[test]
+    fn segment_behind_the_camera_is_dropped() {
+        let projection = projection_at(0.0, 1000.0);
+        let eye_z = 1000.0 - projection.get_fov();
+        let start = Point3D::new(-100.0, 0.0, eye_z - 10.0);
+        let end = Point3D::new(100.0, 0.0, eye_z);
+        assert_eq! {projection.project_segment(&start, &end), None}
+        assert_eq! {projection.project_point(&start), None}
+    }
+
+    #[test]
+    fn level_orientation_keeps_the_flat_projection() {
+        let mut projection = projection_at(30.0, 200.0);
+        projection.set_orientation(0.0, 0.0, 0.0);
+        let point = Point3D::new(-70.0, -200.0, 900.0);
+        let scale = projection.get_fov() / (900.0 - 200.0 + projection.get_fov());
+        assert_eq! {
+            projection.project_point(&point),
+            Some(engine::PointScreen { x: -100.0 * scale, y: -200.0 * scale })
+        }
+    }
+
+    #[test]
+    fn rolling_with_the_ship_levels_its_wings() {
+        let mut projection = projection_at(0.0, 0.0);
+        let roll = 0.30;
+        projection.set_orientation(0.0, 0.0, roll);
+        let corners = crate::player::triangle_corners((0.0, 200.0), -50.0, roll);
+        let right = projection.project_point(&corners[1]).unwrap();
+        let left = projection.project_point(&corners[2]).unwrap();
+        assert! {(right.y - left.y).abs() < 1e-4}
+        // The horizon banks the other way, up on the right
+        let horizon = projection.project_point(&Point3D::new(500.0, 0.0, 3000.0));
+        assert! {horizon.unwrap().y > 0.0}
+    }
+
+    #[test]
+    fn yaw_and_pitch_centre_what_the_camera_turns_to() {
+        let mut projection = projection_at(0.0, 0.0);
+        let depth = 1000.0;
+        let eye_z = -projection.get_fov();
+        let angle: f32 = 0.40;
+        projection.set_orientation(angle, 0.0, 0.0);
+        let right = Point3D::new(depth * angle.tan(), 0.0, eye_z + depth);
+        assert! {projection.project_point(&right).unwrap().x.abs() < 1e-3}
+        projection.set_orientation(0.0, angle, 0.0);
+        let below = Point3D::new(0.0, -depth * angle.tan(), eye_z + depth);
+        assert! {projection.project_point(&below).unwrap().y.abs() < 1e-3}
+        // Turned away, what was straight ahead ends up behind the near plane
+        projection.set_orientation(std::f32::consts::PI, 0.0, 0.0);
+        assert_eq! {projection.project_point(&Point3D::new(0.0, 0.0, depth)), None}
+    }
+
+    #[test]
+    fn rolled_view_zone_covers_the_screen_corners() {
+        let mut projection = projection_at(0.0, 0.0);
+        let level_edge = projection.get_view_zone_x_range(1000.0).1;
+        projection.set_orientation(0.0, 0.0, 0.50);
+        let rolled_edge = projection.get_view_zone_x_range(1000.0).1;
+        assert! {rolled_edge > level_edge}
+        // The top right screen corner lands on the widened edge
+        let (sin_roll, cos_roll) = 0.50_f32.sin_cos();
+        let scale = (1000.0 + projection.get_fov()) / projection.get_fov();
+        let y = (300.0 * cos_roll - 400.0 * sin_roll) * scale;
+        let corner = projection.project_point(&Point3D::new(rolled_edge, y, 1000.0));
+        let corner = corner.unwrap();
+        assert! {(corner.x - 400.0).abs() < 1e-2 && (corner.y - 300.0).abs() < 1e-2}
+    }
+
+    #[test]
+    fn orthographic_lens_ignores_depth() {
+        let mut projection = projection_at(0.0, 0.0);
+        projection.set_lens(Lens {
+            eye_height: 0.0,
+            eye_behind: 0.0,
+            orthographic_scale: Some(0.50),
+        });
+        let near = projection.project_point(&Point3D::new(100.0, -40.0, 10.0));
+        let far = projection.project_point(&Point3D::new(100.0, -40.0, 3000.0));
+        assert_eq! {near, far}
+        assert_eq! {near, Some(engine::PointScreen { x: 50.0, y: -20.0 })}
+        let reach = 0.50 * 1000.0 / 0.50;
+        assert_eq! {projection.get_view_zone_x_range(3000.0), (-reach, reach)}
+        assert_eq! {projection.get_view_zone_z_range(), (-reach, reach)}
     }
 }
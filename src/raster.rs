@@ -0,0 +1,328 @@
+use super::engine;
+use super::renderer;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const FONT_BYTES: &[u8] = include_bytes!("../assets/ProggyClean.ttf");
+
+pub struct Framebuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![[0, 0, 0, 255]; width * height],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn fill(&mut self, color: engine::Color) {
+        let rgba = to_rgba(color, 1.0);
+        for pixel in self.pixels.iter_mut() {
+            *pixel = rgba;
+        }
+    }
+
+    fn blend_pixel(&mut self, x: i32, y: i32, color: engine::Color, coverage: f32) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let alpha = (color.a * coverage).clamp(0.0, 1.0);
+        let src = to_rgba(color, 1.0);
+        let dst = &mut self.pixels[y as usize * self.width + x as usize];
+        for channel in 0..3 {
+            let mixed = src[channel] as f32 * alpha + dst[channel] as f32 * (1.0 - alpha);
+            dst[channel] = mixed.round() as u8;
+        }
+        dst[3] = 255;
+    }
+
+    pub fn write_png(&self, path: &str) {
+        self.to_image().export_png(path);
+    }
+
+    #[cfg(test)]
+    pub fn from_png_bytes(bytes: &[u8]) -> Framebuffer {
+        let image = macroquad::texture::Image::from_file_with_format(bytes, None);
+        Framebuffer {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.get_image_data().to_vec(),
+        }
+    }
+
+    fn to_image(&self) -> macroquad::texture::Image {
+        // export_png flips rows, as it expects images read back from the GPU
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for row in self.pixels.chunks(self.width).rev() {
+            for pixel in row {
+                bytes.extend_from_slice(pixel);
+            }
+        }
+        macroquad::texture::Image {
+            bytes,
+            width: self.width as u16,
+            height: self.height as u16,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn count_different_pixels(&self, other: &Framebuffer, tolerance: u8) -> usize {
+        if self.width != other.width || self.height != other.height {
+            return self.pixels.len().max(other.pixels.len());
+        }
+        self.pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .filter(|(a, b)| (0..4).any(|c| a[c].abs_diff(b[c]) > tolerance))
+            .count()
+    }
+}
+
+fn to_rgba(color: engine::Color, alpha: f32) -> [u8; 4] {
+    [
+        (color.r * 255.0).round() as u8,
+        (color.g * 255.0).round() as u8,
+        (color.b * 255.0).round() as u8,
+        (color.a * alpha * 255.0).round() as u8,
+    ]
+}
+
+pub struct RasterRenderer {
+    framebuffer: Rc<RefCell<Framebuffer>>,
+    font: fontdue::Font,
+}
+
+impl RasterRenderer {
+    pub fn new(width: usize, height: usize) -> RasterRenderer {
+        RasterRenderer {
+            framebuffer: Rc::new(RefCell::new(Framebuffer::new(width, height))),
+            font: fontdue::Font::from_bytes(FONT_BYTES, fontdue::FontSettings::default())
+                .expect("embedded font is valid"),
+        }
+    }
+
+    pub fn framebuffer(&self) -> Rc<RefCell<Framebuffer>> {
+        Rc::clone(&self.framebuffer)
+    }
+
+    // Pixel (i, j) spans i..i + 1 and j..j + 1, so its centre sits half a pixel in.
+    // The pixel under the point is always drawn, thin lines would have gaps otherwise
+    fn stamp(&self, x: f32, y: f32, half_width: f32, color: engine::Color, coverage: f32) {
+        let mut framebuffer = self.framebuffer.borrow_mut();
+        let (px, py) = (x.floor() as i32, y.floor() as i32);
+        let r = half_width.ceil() as i32;
+        for j in py - r..=py + r {
+            for i in px - r..=px + r {
+                let dx = i as f32 + 0.50 - x;
+                let dy = j as f32 + 0.50 - y;
+                if (i, j) == (px, py) || dx * dx + dy * dy < half_width * half_width {
+                    framebuffer.blend_pixel(i, j, color, coverage);
+                }
+            }
+        }
+    }
+}
+
+impl renderer::Renderer for RasterRenderer {
+    fn screen_size(&self) -> (f32, f32) {
+        let framebuffer = self.framebuffer.borrow();
+        (framebuffer.width as f32, framebuffer.height as f32)
+    }
+
+    fn clear(&mut self, color: engine::Color) {
+        self.framebuffer.borrow_mut().fill(color);
+    }
+
+    fn draw_line(
+        &mut self,
+        p1: &engine::PointScreen,
+        p2: &engine::PointScreen,
+        params: &engine::DrawParameters,
+    ) {
+        if !(p1.x.is_finite() && p1.y.is_finite() && p2.x.is_finite() && p2.y.is_finite()) {
+            return;
+        }
+        // Lines thinner than a pixel are drawn one pixel wide, but fainter
+        let coverage = params.line_width.min(1.0);
+        let half_width = 0.50 * params.line_width;
+        // Only the part on screen is stepped along, however far the ends reach
+        let (w, h) = self.screen_size();
+        let margin = half_width + 1.0;
+        let (x_low, x_high) = match clip_axis(p1.x, p2.x, -margin, w + margin) {
+            Some(range) => range,
+            None => return,
+        };
+        let (y_low, y_high) = match clip_axis(p1.y, p2.y, -margin, h + margin) {
+            Some(range) => range,
+            None => return,
+        };
+        let (t_start, t_end) = (x_low.max(y_low), x_high.min(y_high));
+        if t_start > t_end {
+            return;
+        }
+        let dx = p2.x - p1.x;
+        let dy = p2.y - p1.y;
+        let start = (p1.x + t_start * dx, p1.y + t_start * dy);
+        let length = (t_end - t_start) * dx.abs().max(dy.abs());
+        let n_steps = length.ceil().max(1.0) as i32;
+        for step in 0..=n_steps {
+            let t = (t_end - t_start) * step as f32 / n_steps as f32;
+            let (x, y) = (start.0 + t * dx, start.1 + t * dy);
+            self.stamp(x, y, half_width, params.color, coverage);
+        }
+    }
+
+    fn draw_text(
+        &mut self,
+        message: &str,
+        p: &engine::PointScreen,
+        params: &engine::TextParameters,
+    ) {
+        let mut framebuffer = self.framebuffer.borrow_mut();
+        let mut pen_x = p.x;
+        for character in message.chars() {
+            let (metrics, bitmap) = self.font.rasterize(character, params.font_size);
+            let left = pen_x.round() as i32 + metrics.xmin;
+            let top = p.y.round() as i32 - metrics.ymin - metrics.height as i32;
+            for row in 0..metrics.height {
+                for col in 0..metrics.width {
+                    let coverage = bitmap[row * metrics.width + col] as f32 / 255.0;
+                    if coverage > 0.0 {
+                        framebuffer.blend_pixel(
+                            left + col as i32,
+                            top + row as i32,
+                            params.color,
+                            coverage,
+                        );
+                    }
+                }
+            }
+            pen_x += metrics.advance_width;
+        }
+    }
+}
+
+// Part of the segment from `start` to `end`, as a 0..=1 range, between `low` and `high`
+fn clip_axis(start: f32, end: f32, low: f32, high: f32) -> Option<(f32, f32)> {
+    let delta = end - start;
+    if delta == 0.0 {
+        return (low..=high).contains(&start).then_some((0.0, 1.0));
+    }
+    let t_low = (low - start) / delta;
+    let t_high = (high - start) / delta;
+    let range = (t_low.min(t_high).max(0.0), t_low.max(t_high).min(1.0));
+    (range.0 <= range.1).then_some(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: engine::Color = macroquad::prelude::RED;
+
+    #[test]
+    fn clear_fills_background() {
+        let mut raster = RasterRenderer::new(8, 8);
+        renderer::Renderer::clear(&mut raster, RED);
+        let pixel = raster.framebuffer().borrow().get_pixel(3, 3);
+        assert_eq! {pixel, to_rgba(RED, 1.0)}
+    }
+
+    #[test]
+    fn horizontal_line_is_rasterized() {
+        let mut raster = RasterRenderer::new(10, 10);
+        renderer::Renderer::draw_line(
+            &mut raster,
+            &engine::PointScreen { x: 1.0, y: 5.0 },
+            &engine::PointScreen { x: 8.0, y: 5.0 },
+            &engine::PHOENIX_LINE,
+        );
+        let framebuffer = raster.framebuffer();
+        let framebuffer = framebuffer.borrow();
+        for x in 1..=8 {
+            assert_eq! {framebuffer.get_pixel(x, 5), to_rgba(RED, 1.0)}
+        }
+        assert_eq! {framebuffer.get_pixel(5, 0), [0, 0, 0, 255]}
+    }
+
+    #[test]
+    fn lines_are_as_wide_as_their_style() {
+        for (params, rows) in [(engine::DEFAULT_LINE, 5..6), (engine::PHOENIX_LINE, 4..6)] {
+            let mut raster = RasterRenderer::new(10, 10);
+            renderer::Renderer::draw_line(
+                &mut raster,
+                &engine::PointScreen { x: 1.0, y: 5.0 },
+                &engine::PointScreen { x: 8.0, y: 5.0 },
+                &params,
+            );
+            let framebuffer = raster.framebuffer();
+            let framebuffer = framebuffer.borrow();
+            let drawn: Vec<usize> = (0..10)
+                .filter(|&y| framebuffer.get_pixel(4, y) != [0, 0, 0, 255])
+                .collect();
+            assert_eq! {drawn, rows.collect::<Vec<usize>>()}
+        }
+    }
+
+    #[test]
+    fn far_reaching_line_is_clipped_to_the_screen() {
+        let mut raster = RasterRenderer::new(10, 10);
+        renderer::Renderer::draw_line(
+            &mut raster,
+            &engine::PointScreen { x: 5.0, y: 5.0 },
+            &engine::PointScreen { x: 1e9, y: 5.0 },
+            &engine::DEFAULT_LINE,
+        );
+        let framebuffer = raster.framebuffer();
+        let framebuffer = framebuffer.borrow();
+        for x in 5..10 {
+            assert_eq! {framebuffer.get_pixel(x, 5), to_rgba(engine::DEFAULT_LINE.color, 1.0)}
+        }
+        assert_eq! {framebuffer.get_pixel(4, 5), [0, 0, 0, 255]}
+    }
+
+    #[test]
+    fn text_is_rasterized() {
+        let mut raster = RasterRenderer::new(100, 40);
+        renderer::Renderer::draw_text(
+            &mut raster,
+            "Phoenix",
+            &engine::PointScreen { x: 5.0, y: 30.0 },
+            &engine::TEXT_DEFAULT,
+        );
+        let blank = Framebuffer::new(100, 40);
+        let n_text_pixels = raster
+            .framebuffer()
+            .borrow()
+            .count_different_pixels(&blank, 0);
+        assert! {n_text_pixels > 0}
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let mut raster = RasterRenderer::new(16, 12);
+        renderer::Renderer::draw_line(
+            &mut raster,
+            &engine::PointScreen { x: 0.0, y: 0.0 },
+            &engine::PointScreen { x: 15.0, y: 11.0 },
+            &engine::DEFAULT_LINE,
+        );
+        let path = std::env::temp_dir().join("phoenix_raster_round_trip.png");
+        let path = path.to_str().unwrap();
+        let framebuffer = raster.framebuffer();
+        let framebuffer = framebuffer.borrow();
+        framebuffer.write_png(path);
+        let loaded = Framebuffer::from_png_bytes(&std::fs::read(path).unwrap());
+        assert_eq! {loaded.count_different_pixels(&framebuffer, 0), 0}
+    }
+}
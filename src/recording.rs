@@ -0,0 +1,144 @@
+use super::engine;
+use super::renderer;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecordedLine {
+    pub p1: engine::PointScreen,
+    pub p2: engine::PointScreen,
+    pub params: engine::DrawParameters,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct RecordedText {
+    pub message: String,
+    pub anchor: engine::PointScreen,
+    pub params: engine::TextParameters,
+}
+
+#[derive(Default)]
+pub struct RecordedFrame {
+    pub lines: Vec<RecordedLine>,
+    pub texts: Vec<RecordedText>,
+}
+
+impl RecordedFrame {
+    pub fn has_text(&self, message: &str) -> bool {
+        self.texts.iter().any(|text| text.message.contains(message))
+    }
+}
+
+pub struct RecordingRenderer {
+    screen_size: (f32, f32),
+    frame: Rc<RefCell<RecordedFrame>>,
+}
+
+impl RecordingRenderer {
+    pub fn new(screen_width: f32, screen_height: f32) -> RecordingRenderer {
+        RecordingRenderer {
+            screen_size: (screen_width, screen_height),
+            frame: Rc::new(RefCell::new(RecordedFrame::default())),
+        }
+    }
+
+    pub fn frame(&self) -> Rc<RefCell<RecordedFrame>> {
+        Rc::clone(&self.frame)
+    }
+}
+
+impl renderer::Renderer for RecordingRenderer {
+    fn screen_size(&self) -> (f32, f32) {
+        self.screen_size
+    }
+
+    fn clear(&mut self, _color: engine::Color) {
+        let mut frame = self.frame.borrow_mut();
+        frame.lines.clear();
+        frame.texts.clear();
+    }
+
+    fn draw_line(
+        &mut self,
+        p1: &engine::PointScreen,
+        p2: &engine::PointScreen,
+        params: &engine::DrawParameters,
+    ) {
+        self.frame.borrow_mut().lines.push(RecordedLine {
+            p1: *p1,
+            p2: *p2,
+            params: *params,
+        });
+    }
+
+    fn draw_text(
+        &mut self,
+        message: &str,
+        p: &engine::PointScreen,
+        params: &engine::TextParameters,
+    ) {
+        self.frame.borrow_mut().texts.push(RecordedText {
+            message: message.to_string(),
+            anchor: *p,
+            params: *params,
+        });
+    }
+}
+
+// Draws from now on land in the returned frame
+pub(crate) fn install_recorder() -> Rc<RefCell<RecordedFrame>> {
+    let renderer = RecordingRenderer::new(800.0, 600.0);
+    let frame = renderer.frame();
+    engine::set_renderer(Box::new(renderer));
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn engine_reports_renderer_screen_size() {
+        engine::set_renderer(Box::new(RecordingRenderer::new(640.0, 480.0)));
+        assert_eq! {engine::get_screen_width(), 640.0}
+        assert_eq! {engine::get_screen_height(), 480.0}
+    }
+
+    #[test]
+    fn line_is_recorded_in_screen_coordinates() {
+        let frame = install_recorder();
+        engine::draw_line_personalized(
+            engine::PointScreen { x: 0.0, y: 0.0 },
+            engine::PointScreen { x: 100.0, y: 100.0 },
+            engine::DEFAULT_LINE,
+        );
+        let frame = frame.borrow();
+        assert_eq! {frame.lines.len(), 1}
+        assert_eq! {frame.lines[0].p1, engine::PointScreen { x: 400.0, y: 300.0 }}
+        assert_eq! {frame.lines[0].p2, engine::PointScreen { x: 500.0, y: 200.0 }}
+        assert_eq! {frame.lines[0].params, engine::DEFAULT_LINE}
+    }
+
+    #[test]
+    fn text_is_recorded() {
+        let frame = install_recorder();
+        engine::draw_text(
+            "hello",
+            engine::PointScreen { x: 0.0, y: 0.0 },
+            engine::TEXT_DEFAULT,
+        );
+        assert! {frame.borrow().has_text("hello")}
+    }
+
+    #[test]
+    fn clear_starts_a_new_frame() {
+        let frame = install_recorder();
+        engine::draw_line_personalized(
+            engine::PointScreen { x: 0.0, y: 0.0 },
+            engine::PointScreen { x: 1.0, y: 1.0 },
+            engine::DEFAULT_LINE,
+        );
+        engine::clear_background();
+        assert! {frame.borrow().lines.is_empty()}
+    }
+}
@@ -1,6 +1,7 @@
 use super::engine;
 use super::projection;
 
+#[derive(Clone, PartialEq, Debug)]
 pub struct Rectangle {
     center: (f32, f32),
     size: (f32, f32),
@@ -23,6 +24,57 @@ impl Rectangle {
         false
     }
 
+    // Fraction of the way from `previous` to `self` where it first touches `other`, only
+    // counting the times in `overlap`, e.g. while also overlapping in height
+    pub fn sweep_within(
+        &self,
+        previous: &Rectangle,
+        other: &Rectangle,
+        overlap: (f32, f32),
+    ) -> Option<f32> {
+        let start = previous.center;
+        let end = self.center;
+        let x_interval = Rectangle::sweep_axis(
+            start.0,
+            end.0,
+            other.center.0,
+            0.50 * (self.size.0 + other.size.0),
+        )?;
+        let z_interval = Rectangle::sweep_axis(
+            start.1,
+            end.1,
+            other.center.1,
+            0.50 * (self.size.1 + other.size.1),
+        )?;
+        let t_entry = x_interval.0.max(z_interval.0).max(overlap.0);
+        let t_exit = x_interval.1.min(z_interval.1).min(overlap.1);
+        if t_entry > t_exit || t_exit < 0.0 || t_entry > 1.0 {
+            return None;
+        }
+        Some(t_entry.max(0.0))
+    }
+
+    pub fn sweep_axis(start: f32, end: f32, center: f32, half_extent: f32) -> Option<(f32, f32)> {
+        let low = center - half_extent;
+        let high = center + half_extent;
+        let delta = end - start;
+        if delta == 0.0 {
+            if start < low || start > high {
+                return None;
+            }
+            return Some((f32::NEG_INFINITY, f32::INFINITY));
+        }
+        let t1 = (low - start) / delta;
+        let t2 = (high - start) / delta;
+        Some((t1.min(t2), t1.max(t2)))
+    }
+
+    pub fn lerp(&self, other: &Rectangle, t: f32) -> Rectangle {
+        let x = self.center.0 + (other.center.0 - self.center.0) * t;
+        let z = self.center.1 + (other.center.1 - self.center.1) * t;
+        Rectangle::new((x, z), other.size)
+    }
+
     pub fn get_corners(&self, y: f32) -> [projection::Point3D; 4] {
         let x_left = self.center.0 - 0.50 * self.size.0;
         let x_righ = self.center.0 + 0.50 * self.size.0;
@@ -37,40 +89,37 @@ impl Rectangle {
         [p1, p2, p3, p4]
     }
 
-    fn get_corners_xz(&self) -> [(f32, f32); 4] {
-        let corners = self.get_corners(0.0);
-        let p1 = (corners[0].x, corners[0].z);
-        let p2 = (corners[1].x, corners[1].z);
-        let p3 = (corners[2].x, corners[2].z);
-        let p4 = (corners[3].x, corners[3].z);
-        [p1, p2, p3, p4]
-    }
-
-    pub fn draw(&self, y: f32, projection: &projection::Projection) {
+    pub fn draw_personalized(
+        &self,
+        y: f32,
+        projection: &projection::Projection,
+        draw_params: engine::DrawParameters,
+    ) {
         let corners = self.get_corners(y);
 
-        engine::draw_line(
-            projection.to_screen(&corners[0]),
-            projection.to_screen(&corners[1]),
-        );
-        engine::draw_line(
-            projection.to_screen(&corners[1]),
-            projection.to_screen(&corners[2]),
-        );
-        engine::draw_line(
-            projection.to_screen(&corners[2]),
-            projection.to_screen(&corners[3]),
-        );
-        engine::draw_line(
-            projection.to_screen(&corners[3]),
-            projection.to_screen(&corners[0]),
-        );
+        for i in 0..4 {
+            projection.draw_segment(&corners[i], &corners[(i + 1) % 4], draw_params);
+        }
     }
 
     pub fn get_center(&self) -> (f32, f32) {
         self.center
     }
 
+    pub fn get_size(&self) -> (f32, f32) {
+        self.size
+    }
+
+    pub fn get_x_range(&self) -> (f32, f32) {
+        let half_width = self.size.0 * 0.50;
+        (self.center.0 - half_width, self.center.0 + half_width)
+    }
+
+    pub fn get_z_range(&self) -> (f32, f32) {
+        let half_depth = self.size.1 * 0.50;
+        (self.center.1 - half_depth, self.center.1 + half_depth)
+    }
+
     pub fn move_x(&mut self, delta_x: f32) {
         self.center.0 += delta_x;
     }
@@ -80,19 +129,15 @@ impl Rectangle {
     }
 
     pub fn is_rectangle_in_view_range(&self, projection: &projection::Projection) -> bool {
-        let mut in_view: bool = false;
-        for corner in self.get_corners_xz() {
-            if projection.is_point_in_view_zone(&corner) {
-                in_view = true;
-            }
-        }
-        in_view
+        projection.is_area_in_view_zone(self.get_x_range(), self.get_z_range())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
+    use crate::recording;
 
     #[test]
     fn collision_true() {
@@ -122,6 +167,52 @@ mod tests {
         assert_eq! { rec1.collision(&rec2), false }
     }
 
+    const ANY_TIME: (f32, f32) = (f32::NEG_INFINITY, f32::INFINITY);
+
+    #[test]
+    fn sweep_hits_thin_wall_between_frames() {
+        let wall = Rectangle::new((0.0, 20.0), (100.0, 2.0));
+        let previous = Rectangle::new((0.0, 0.0), (10.0, 10.0));
+        let current = Rectangle::new((0.0, 40.0), (10.0, 10.0));
+        assert! { !current.collision(&wall) }
+        let toi = current.sweep_within(&previous, &wall, ANY_TIME).unwrap();
+        assert! { (toi - 14.0 / 40.0).abs() < 1e-6 }
+    }
+
+    #[test]
+    fn sweep_misses_wall_beside_path() {
+        let wall = Rectangle::new((50.0, 20.0), (10.0, 2.0));
+        let previous = Rectangle::new((0.0, 0.0), (10.0, 10.0));
+        let current = Rectangle::new((0.0, 40.0), (10.0, 10.0));
+        assert! { current.sweep_within(&previous, &wall, ANY_TIME).is_none() }
+    }
+
+    #[test]
+    fn sweep_hits_corner_on_diagonal() {
+        let block = Rectangle::new((20.0, 20.0), (10.0, 10.0));
+        let previous = Rectangle::new((0.0, 0.0), (4.0, 4.0));
+        let current = Rectangle::new((40.0, 40.0), (4.0, 4.0));
+        assert! { !current.collision(&block) }
+        assert! { current.sweep_within(&previous, &block, ANY_TIME).is_some() }
+    }
+
+    #[test]
+    fn sweep_starting_inside_hits_immediately() {
+        let block = Rectangle::new((0.0, 0.0), (10.0, 10.0));
+        let previous = Rectangle::new((0.0, 0.0), (2.0, 2.0));
+        let current = Rectangle::new((0.0, 3.0), (2.0, 2.0));
+        assert_eq! { current.sweep_within(&previous, &block, ANY_TIME), Some(0.0) }
+    }
+
+    #[test]
+    fn sweep_without_motion_matches_collision() {
+        let block = Rectangle::new((0.0, 0.0), (1.0, 1.0));
+        let touching = Rectangle::new((1.0, 0.0), (1.0, 1.0));
+        let apart = Rectangle::new((3.0, 0.0), (1.0, 1.0));
+        assert! { touching.sweep_within(&touching, &block, ANY_TIME).is_some() }
+        assert! { apart.sweep_within(&apart, &block, ANY_TIME).is_none() }
+    }
+
     #[test]
     fn move_center_x() {
         let mut rec1 = Rectangle::new((0.0, 0.0), (1.0, 1.0));
@@ -151,4 +242,34 @@ mod tests {
         rec1.move_y(-20.0);
         assert_eq! { rec1.get_center(), (0.0, -20.0) }
     }
+
+    #[test]
+    fn draw_outlines_four_sides() {
+        let frame = recording::install_recorder();
+        let rec1 = Rectangle::new((0.0, 100.0), (50.0, 50.0));
+        rec1.draw_personalized(
+            -100.0,
+            &projection::Projection::new(100.0),
+            engine::GRID_LINE,
+        );
+        let n_lines = frame.borrow().lines.len();
+        assert_eq! { n_lines, 4 }
+    }
+
+    #[test]
+    fn draw_clips_sides_reaching_behind_the_camera() {
+        let frame = recording::install_recorder();
+        let fence = Rectangle::new((200.0, 0.0), (40.0, 2000.0));
+        fence.draw_personalized(
+            -100.0,
+            &projection::Projection::new(100.0),
+            engine::GRID_LINE,
+        );
+        let frame = frame.borrow();
+        // The near edge is behind the camera, the sides are cut short
+        assert_eq! { frame.lines.len(), 3 }
+        // In pixels, the ground stays below the horizon and right of centre instead of flipping
+        assert! { frame.lines.iter().all(|line| line.p1.y > 300.0 && line.p2.y > 300.0) }
+        assert! { frame.lines.iter().all(|line| line.p1.x > 400.0 && line.p2.x > 400.0) }
+    }
 }
@@ -0,0 +1,64 @@
+use super::engine;
+#[cfg(test)]
+use super::recording;
+
+pub trait Renderer {
+    fn screen_size(&self) -> (f32, f32);
+    fn clear(&mut self, color: engine::Color);
+    fn draw_line(
+        &mut self,
+        p1: &engine::PointScreen,
+        p2: &engine::PointScreen,
+        params: &engine::DrawParameters,
+    );
+    fn draw_text(
+        &mut self,
+        message: &str,
+        p: &engine::PointScreen,
+        params: &engine::TextParameters,
+    );
+}
+
+#[cfg_attr(test, allow(dead_code))]
+pub struct MacroquadRenderer;
+
+impl Renderer for MacroquadRenderer {
+    fn screen_size(&self) -> (f32, f32) {
+        (
+            macroquad::prelude::screen_width(),
+            macroquad::prelude::screen_height(),
+        )
+    }
+
+    fn clear(&mut self, color: engine::Color) {
+        macroquad::prelude::clear_background(color);
+    }
+
+    fn draw_line(
+        &mut self,
+        p1: &engine::PointScreen,
+        p2: &engine::PointScreen,
+        params: &engine::DrawParameters,
+    ) {
+        macroquad::shapes::draw_line(p1.x, p1.y, p2.x, p2.y, params.line_width, params.color);
+    }
+
+    fn draw_text(
+        &mut self,
+        message: &str,
+        p: &engine::PointScreen,
+        params: &engine::TextParameters,
+    ) {
+        macroquad::prelude::draw_text(message, p.x, p.y, params.font_size, params.color);
+    }
+}
+
+#[cfg(not(test))]
+pub fn default_renderer() -> Box<dyn Renderer> {
+    Box::new(MacroquadRenderer)
+}
+
+#[cfg(test)]
+pub fn default_renderer() -> Box<dyn Renderer> {
+    Box::new(recording::RecordingRenderer::new(800.0, 600.0))
+}
@@ -0,0 +1,343 @@
+use super::engine;
+use super::hash;
+use super::tuning;
+
+pub const LAST_RUN_FILE: &str = "last_run.phxr";
+
+const MAGIC: &[u8; 4] = b"PHXR";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 4 + 1 + 8 + 4 + 4 + 4 + 8 + 8 + 4;
+const FRAME_SIZE: usize = 8 + 2 + 2;
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{}", error),
+            ReplayError::BadMagic => write!(f, "not a phoenix replay"),
+            ReplayError::UnsupportedVersion(version) => {
+                write!(f, "unsupported replay version {}", version)
+            }
+            ReplayError::Truncated => write!(f, "replay file is truncated"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ReplayHeader {
+    pub seed: u64,
+    pub camera_drop: f32,
+    pub map_width: f32,
+    pub map_length: f32,
+    pub start_time: f64,
+    pub tuning_hash: u64,
+}
+
+impl ReplayHeader {
+    pub fn matches_tuning(&self, tuning: &tuning::Tuning) -> bool {
+        self.tuning_hash == tuning_hash(tuning)
+    }
+}
+
+pub fn tuning_hash(tuning: &tuning::Tuning) -> u64 {
+    hash::content_hash(&tuning.to_text())
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct InputFrame {
+    pub time: f64,
+    pub keys: engine::MoveKeys,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Recording {
+    header: ReplayHeader,
+    frames: Vec<InputFrame>,
+}
+
+impl Recording {
+    pub fn new(header: ReplayHeader) -> Recording {
+        Recording {
+            header,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn get_header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    pub fn frames(&self) -> &[InputFrame] {
+        &self.frames
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + FRAME_SIZE * self.frames.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.header.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.header.camera_drop.to_le_bytes());
+        bytes.extend_from_slice(&self.header.map_width.to_le_bytes());
+        bytes.extend_from_slice(&self.header.map_length.to_le_bytes());
+        bytes.extend_from_slice(&self.header.start_time.to_le_bytes());
+        bytes.extend_from_slice(&self.header.tuning_hash.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.time.to_le_bytes());
+            bytes.push(keys_to_bits(&frame.keys));
+            bytes.push(frame.keys.hop as u8);
+            bytes.push(axis_to_byte(frame.keys.steer_axis));
+            bytes.push(axis_to_byte(frame.keys.throttle_axis));
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Recording, ReplayError> {
+        let mut reader = ByteReader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let header = ReplayHeader {
+            seed: u64::from_le_bytes(reader.take_array()?),
+            camera_drop: f32::from_le_bytes(reader.take_array()?),
+            map_width: f32::from_le_bytes(reader.take_array()?),
+            map_length: f32::from_le_bytes(reader.take_array()?),
+            start_time: f64::from_le_bytes(reader.take_array()?),
+            tuning_hash: u64::from_le_bytes(reader.take_array()?),
+        };
+        let n_frames = u32::from_le_bytes(reader.take_array()?);
+        let mut recording = Recording::new(header);
+        for _ in 0..n_frames {
+            let time = f64::from_le_bytes(reader.take_array()?);
+            let mut keys = bits_to_keys(reader.take(1)?[0]);
+            keys.hop = reader.take(1)?[0] & 1 != 0;
+            keys.steer_axis = byte_to_axis(reader.take(1)?[0]);
+            keys.throttle_axis = byte_to_axis(reader.take(1)?[0]);
+            recording.frames.push(InputFrame { time, keys });
+        }
+        Ok(recording)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ReplayError> {
+        std::fs::write(path, self.to_bytes()).map_err(ReplayError::Io)
+    }
+
+    pub fn load(path: &str) -> Result<Recording, ReplayError> {
+        let bytes = std::fs::read(path).map_err(ReplayError::Io)?;
+        Recording::from_bytes(&bytes)
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl ByteReader<'_> {
+    fn take(&mut self, n_bytes: usize) -> Result<&[u8], ReplayError> {
+        let end = self.position + n_bytes;
+        if end > self.bytes.len() {
+            return Err(ReplayError::Truncated);
+        }
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
+
+fn keys_to_bits(keys: &engine::MoveKeys) -> u8 {
+    (keys.up as u8)
+        | (keys.down as u8) << 1
+        | (keys.left as u8) << 2
+        | (keys.right as u8) << 3
+        | (keys.pause as u8) << 4
+        | (keys.confirm as u8) << 5
+        | (keys.restart as u8) << 6
+        | (keys.touch as u8) << 7
+}
+
+fn bits_to_keys(bits: u8) -> engine::MoveKeys {
+    engine::MoveKeys {
+        up: bits & 1 != 0,
+        down: bits & (1 << 1) != 0,
+        left: bits & (1 << 2) != 0,
+        right: bits & (1 << 3) != 0,
+        pause: bits & (1 << 4) != 0,
+        confirm: bits & (1 << 5) != 0,
+        restart: bits & (1 << 6) != 0,
+        touch: bits & (1 << 7) != 0,
+        ..engine::MoveKeys::NONE
+    }
+}
+
+// Axes are quantized by the input, so the round trip is exact
+fn axis_to_byte(axis: f32) -> u8 {
+    ((axis * engine::AXIS_STEPS).round() as i8) as u8
+}
+
+fn byte_to_axis(byte: u8) -> f32 {
+    (byte as i8) as f32 / engine::AXIS_STEPS
+}
+
+pub struct Recorder {
+    recording: Option<Recording>,
+}
+
+impl Recorder {
+    pub fn new() -> Recorder {
+        Recorder { recording: None }
+    }
+
+    pub fn start(&mut self, header: ReplayHeader) {
+        self.recording = Some(Recording::new(header));
+    }
+
+    pub fn record(&mut self, time: f64, keys: &engine::MoveKeys) {
+        if let Some(recording) = &mut self.recording {
+            recording.frames.push(InputFrame { time, keys: *keys });
+        }
+    }
+
+    pub fn finish(&mut self) -> Option<Recording> {
+        self.recording.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: ReplayHeader = ReplayHeader {
+        seed: 1234,
+        camera_drop: 200.0,
+        map_width: 1000.0,
+        map_length: 5000.0,
+        start_time: 0.5,
+        tuning_hash: 0x0123456789abcdef,
+    };
+
+    const UP_LEFT_PRESS: engine::MoveKeys = engine::MoveKeys {
+        up: true,
+        left: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn recording_round_trips_through_bytes() {
+        let mut recorder = Recorder::new();
+        recorder.start(HEADER);
+        recorder.record(0.52, &UP_LEFT_PRESS);
+        recorder.record(0.54, &UP_LEFT_PRESS);
+        let recording = recorder.finish().unwrap();
+        let decoded = Recording::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq! {decoded, recording}
+    }
+
+    #[test]
+    fn analog_axes_round_trip_through_bytes() {
+        let mut recorder = Recorder::new();
+        recorder.start(HEADER);
+        let keys = engine::MoveKeys {
+            steer_axis: engine::quantize_axis(-0.37),
+            throttle_axis: engine::quantize_axis(0.81),
+            ..engine::MoveKeys::NONE
+        };
+        recorder.record(0.52, &keys);
+        let recording = recorder.finish().unwrap();
+        let decoded = Recording::from_bytes(&recording.to_bytes()).unwrap();
+        assert_eq! {decoded.frames()[0].keys, keys}
+    }
+
+    #[test]
+    fn replay_checks_its_tuning() {
+        let default = tuning::Tuning::DEFAULT;
+        let retuned = tuning::Tuning {
+            player: tuning::PlayerParams {
+                max_speed: 3000.0,
+                ..default.player
+            },
+            ..default
+        };
+        let header = ReplayHeader {
+            tuning_hash: tuning_hash(&default),
+            ..HEADER
+        };
+        assert! {header.matches_tuning(&default)}
+        assert! {!header.matches_tuning(&retuned)}
+    }
+
+    #[test]
+    fn hop_round_trips_through_bytes() {
+        let mut recorder = Recorder::new();
+        recorder.start(HEADER);
+        let hop = engine::MoveKeys {
+            hop: true,
+            ..engine::MoveKeys::NONE
+        };
+        recorder.record(0.52, &hop);
+        let decoded = Recording::from_bytes(&recorder.finish().unwrap().to_bytes()).unwrap();
+        assert_eq! {decoded.frames()[0].keys, hop}
+    }
+
+    #[test]
+    fn recorder_ignores_input_before_start() {
+        let mut recorder = Recorder::new();
+        recorder.record(0.1, &UP_LEFT_PRESS);
+        assert! {recorder.finish().is_none()}
+    }
+
+    #[test]
+    fn frames_are_compact() {
+        let mut recorder = Recorder::new();
+        recorder.start(HEADER);
+        for i in 0..100 {
+            recorder.record(i as f64, &UP_LEFT_PRESS);
+        }
+        let bytes = recorder.finish().unwrap().to_bytes();
+        assert_eq! {bytes.len(), HEADER_SIZE + 100 * FRAME_SIZE}
+    }
+
+    #[test]
+    fn reject_bad_magic() {
+        let mut bytes = Recording::new(HEADER).to_bytes();
+        bytes[0] = b'X';
+        assert! {matches!(Recording::from_bytes(&bytes), Err(ReplayError::BadMagic))}
+    }
+
+    #[test]
+    fn reject_other_versions() {
+        let mut bytes = Recording::new(HEADER).to_bytes();
+        bytes[4] = VERSION + 1;
+        assert! {matches!(
+            Recording::from_bytes(&bytes),
+            Err(ReplayError::UnsupportedVersion(version)) if version == VERSION + 1
+        )}
+    }
+
+    #[test]
+    fn reject_truncated_file() {
+        let mut recorder = Recorder::new();
+        recorder.start(HEADER);
+        recorder.record(0.52, &UP_LEFT_PRESS);
+        let bytes = recorder.finish().unwrap().to_bytes();
+        let truncated = &bytes[..bytes.len() - 1];
+        assert! {matches!(Recording::from_bytes(truncated), Err(ReplayError::Truncated))}
+    }
+}
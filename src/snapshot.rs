@@ -0,0 +1,140 @@
+use super::engine;
+use super::level;
+use super::mapfile;
+use super::raster;
+use super::tuning;
+use std::rc::Rc;
+
+pub const THUMBNAIL_WIDTH: usize = 800;
+pub const THUMBNAIL_HEIGHT: usize = 600;
+
+pub fn render(width: usize, height: usize, draw: impl FnOnce()) -> raster::Framebuffer {
+    let renderer = raster::RasterRenderer::new(width, height);
+    let framebuffer = renderer.framebuffer();
+    let previous = engine::set_renderer(Box::new(renderer));
+    engine::clear_background();
+    draw();
+    engine::set_renderer(previous);
+    Rc::try_unwrap(framebuffer)
+        .ok()
+        .expect("renderer was released")
+        .into_inner()
+}
+
+fn new_level(seed: u64) -> level::Level {
+    level::Level::new(
+        super::camera_drop(),
+        super::map_width(),
+        super::map_length(),
+        seed,
+        tuning::Tuning::DEFAULT,
+        0.0,
+    )
+}
+
+pub fn render_level(seed: u64, width: usize, height: usize) -> raster::Framebuffer {
+    render(width, height, || new_level(seed).draw(1.0))
+}
+
+pub fn export_map(seed: u64) -> mapfile::MapFile {
+    // Map dimensions follow the screen, use the thumbnail size as the default window
+    let mut map_file = None;
+    render(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, || {
+        map_file = Some(new_level(seed).get_map_file())
+    });
+    map_file.expect("map was exported")
+}
+
+// phoenix --thumbnail <seed> <file.png>
+// phoenix --export-map <seed> <file.toml>
+pub fn run_from_args(args: &[String]) -> bool {
+    if args.len() != 4 || (args[1] != "--thumbnail" && args[1] != "--export-map") {
+        return false;
+    }
+    let seed = match args[2].parse::<u64>() {
+        Ok(seed) => seed,
+        Err(_) => {
+            eprintln!("invalid seed: {}", args[2]);
+            return true;
+        }
+    };
+    if args[1] == "--thumbnail" {
+        render_level(seed, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT).write_png(&args[3]);
+        println!("map thumbnail for seed {} written to {}", seed, args[3]);
+    } else {
+        match export_map(seed).save(&args[3]) {
+            Ok(()) => println!("map for seed {} written to {}", seed, args[3]),
+            Err(error) => eprintln!("could not write map {}: {}", args[3], error),
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings;
+    use crate::startmenu;
+
+    const SNAPSHOT_WIDTH: usize = 800;
+    const SNAPSHOT_HEIGHT: usize = 600;
+    const SNAPSHOT_SEED: u64 = 1;
+    const CHANNEL_TOLERANCE: u8 = 8;
+    const MAX_DIFFERENT_PIXELS: usize = 40;
+
+    fn render_start_menu(seed: u64, width: usize, height: usize) -> raster::Framebuffer {
+        render(width, height, || {
+            startmenu::StartMenu::new(seed).draw(&bindings::Bindings::new());
+            new_level(seed).draw(1.0);
+        })
+    }
+
+    fn assert_matches_golden(framebuffer: &raster::Framebuffer, name: &str) {
+        let path = format!("{}/snapshots/{}.png", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var("PHOENIX_UPDATE_SNAPSHOTS").is_ok() {
+            framebuffer.write_png(&path);
+        }
+        let bytes = std::fs::read(&path)
+            .unwrap_or_else(|_| panic!("missing {}, run with PHOENIX_UPDATE_SNAPSHOTS=1", path));
+        let golden = raster::Framebuffer::from_png_bytes(&bytes);
+        let n_different = framebuffer.count_different_pixels(&golden, CHANNEL_TOLERANCE);
+        assert! {
+            n_different <= MAX_DIFFERENT_PIXELS,
+            "{} differs from golden image in {} pixels", name, n_different
+        }
+    }
+
+    #[test]
+    fn level_matches_golden_image() {
+        let framebuffer = render_level(SNAPSHOT_SEED, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT);
+        assert_matches_golden(&framebuffer, "level");
+    }
+
+    #[test]
+    fn start_menu_matches_golden_image() {
+        let framebuffer = render_start_menu(SNAPSHOT_SEED, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT);
+        assert_matches_golden(&framebuffer, "start_menu");
+    }
+
+    #[test]
+    fn different_seeds_render_different_maps() {
+        let map1 = render_level(1, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT);
+        let map2 = render_level(2, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT);
+        assert! {map1.count_different_pixels(&map2, 0) > 0}
+    }
+
+    #[test]
+    fn exported_map_matches_thumbnail_map() {
+        let map_file = export_map(SNAPSHOT_SEED);
+        assert_eq! {map_file.seed, SNAPSHOT_SEED}
+        assert_eq! {map_file.width, 2.0 * THUMBNAIL_WIDTH as f32}
+        assert! {!map_file.obstacles.is_empty()}
+    }
+
+    #[test]
+    fn renderer_is_restored_after_snapshot() {
+        let before = engine::get_screen_width();
+        render_level(SNAPSHOT_SEED, SNAPSHOT_WIDTH, SNAPSHOT_HEIGHT);
+        assert_eq! {engine::get_screen_width(), before}
+    }
+}
@@ -0,0 +1,99 @@
+pub struct ZBuckets {
+    bucket_size: f32,
+    last_bucket: usize,
+    buckets: Vec<Vec<usize>>,
+}
+
+impl ZBuckets {
+    // Anything past the length shares the last bucket
+    pub fn new(bucket_size: f32, length: f32) -> ZBuckets {
+        ZBuckets {
+            bucket_size,
+            last_bucket: (length / bucket_size).floor().max(0.0) as usize,
+            buckets: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, item: usize, z_range: (f32, f32)) {
+        let (first, last) = self.bucket_span(z_range);
+        if self.buckets.len() <= last {
+            self.buckets.resize(last + 1, Vec::new());
+        }
+        for bucket in &mut self.buckets[first..=last] {
+            bucket.push(item);
+        }
+    }
+
+    pub fn query(&self, z_range: (f32, f32)) -> Vec<usize> {
+        let mut items: Vec<usize> = Vec::new();
+        if self.buckets.is_empty() {
+            return items;
+        }
+        let (first, last) = self.bucket_span(z_range);
+        let last = last.min(self.buckets.len() - 1);
+        if first > last {
+            return items;
+        }
+        for bucket in &self.buckets[first..=last] {
+            items.extend_from_slice(bucket);
+        }
+        items.sort_unstable();
+        items.dedup();
+        items
+    }
+
+    fn bucket_span(&self, z_range: (f32, f32)) -> (usize, usize) {
+        let z_low = z_range.0.min(z_range.1);
+        let z_high = z_range.0.max(z_range.1);
+        (self.bucket_of(z_low), self.bucket_of(z_high))
+    }
+
+    fn bucket_of(&self, z: f32) -> usize {
+        ((z / self.bucket_size).floor().max(0.0) as usize).min(self.last_bucket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_finds_item_in_range() {
+        let mut buckets = ZBuckets::new(100.0, 10000.0);
+        buckets.insert(0, (150.0, 160.0));
+        buckets.insert(1, (950.0, 960.0));
+        assert_eq! {buckets.query((100.0, 200.0)), vec![0]}
+    }
+
+    #[test]
+    fn item_spanning_buckets_is_reported_once() {
+        let mut buckets = ZBuckets::new(100.0, 10000.0);
+        buckets.insert(3, (50.0, 450.0));
+        assert_eq! {buckets.query((0.0, 1000.0)), vec![3]}
+        assert_eq! {buckets.query((320.0, 330.0)), vec![3]}
+    }
+
+    #[test]
+    fn query_beyond_items_is_empty() {
+        let mut buckets = ZBuckets::new(100.0, 10000.0);
+        buckets.insert(0, (10.0, 20.0));
+        assert! {buckets.query((5000.0, 6000.0)).is_empty()}
+        assert! {ZBuckets::new(100.0, 10000.0).query((0.0, 10.0)).is_empty()}
+    }
+
+    #[test]
+    fn negative_z_falls_in_first_bucket() {
+        let mut buckets = ZBuckets::new(100.0, 10000.0);
+        buckets.insert(0, (-50.0, 10.0));
+        assert_eq! {buckets.query((-500.0, -400.0)), vec![0]}
+    }
+
+    #[test]
+    fn z_beyond_the_length_falls_in_last_bucket() {
+        let mut buckets = ZBuckets::new(100.0, 1000.0);
+        buckets.insert(0, (950.0, f32::MAX));
+        buckets.insert(1, (f32::INFINITY, f32::INFINITY));
+        assert_eq! {buckets.buckets.len(), 11}
+        assert_eq! {buckets.query((1e30, 1e30)), vec![0, 1]}
+    }
+}
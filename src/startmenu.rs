@@ -1,3 +1,4 @@
+use super::bindings;
 use super::engine;
 
 const TITLE_ANCHOR: engine::PointScreen = engine::PointScreen {
@@ -9,57 +10,200 @@ const INSTRUCTIONS_X: f32 = -80.0;
 const INSTRUCTIONS_Y1: f32 = 180.0;
 const INSTRUCTIONS_Y_STEP: f32 = -20.0;
 
-const INSTRUCTIONS_ANCHOR: engine::PointScreen = engine::PointScreen {
-    x: INSTRUCTIONS_X,
-    y: INSTRUCTIONS_Y1,
-};
-
-const INSTRUCTIONS_ANCHOR2: engine::PointScreen = engine::PointScreen {
-    x: INSTRUCTIONS_X,
-    y: INSTRUCTIONS_Y1 + INSTRUCTIONS_Y_STEP,
-};
+const INSTRUCTIONS: [(bindings::Action, &str); 9] = [
+    (bindings::Action::Accelerate, "Accelerate"),
+    (bindings::Action::Decelerate, "Decelerate"),
+    (bindings::Action::Left, "Left"),
+    (bindings::Action::Right, "Right"),
+    (bindings::Action::Pause, "Pause"),
+    (bindings::Action::Restart, "Restart"),
+    (bindings::Action::Confirm, "Key bindings"),
+    (bindings::Action::Hop, "Hop"),
+    (bindings::Action::Camera, "Camera view"),
+];
 
-const INSTRUCTIONS_ANCHOR3: engine::PointScreen = engine::PointScreen {
+const SEED_ANCHOR: engine::PointScreen = engine::PointScreen {
     x: INSTRUCTIONS_X,
-    y: INSTRUCTIONS_Y1 + 2.0 * INSTRUCTIONS_Y_STEP,
+    y: INSTRUCTIONS_Y1 + INSTRUCTIONS.len() as f32 * INSTRUCTIONS_Y_STEP,
 };
 
 pub struct StartMenu {
     level_start: bool,
+    bindings_request: bool,
+    seed: u64,
+    course: Option<String>,
+    left_was_down: bool,
+    right_was_down: bool,
+    confirm_was_down: bool,
 }
 
 impl StartMenu {
-    pub fn new() -> StartMenu {
-        StartMenu { level_start: false }
+    pub fn new(seed: u64) -> StartMenu {
+        StartMenu {
+            level_start: false,
+            bindings_request: false,
+            seed,
+            course: None,
+            left_was_down: false,
+            right_was_down: false,
+            confirm_was_down: false,
+        }
+    }
+    pub fn with_course(course: &str, seed: u64) -> StartMenu {
+        let mut menu = StartMenu::new(seed);
+        menu.course = Some(course.to_string());
+        menu
     }
-    pub fn draw(&self) {
+    pub fn draw(&self, bindings: &bindings::Bindings) {
         engine::draw_text("Phoenix", TITLE_ANCHOR, engine::TEXT_TITLE);
-        self.draw_instructions();
+        self.draw_instructions(bindings);
+        self.draw_seed(bindings);
     }
     pub fn update(&mut self, active_keys: &engine::MoveKeys) {
-        if active_keys.up {
+        if active_keys.get_throttle() > 0.0 || active_keys.touch {
             self.level_start = true;
         }
+        if active_keys.confirm && !self.confirm_was_down {
+            self.bindings_request = true;
+        }
+        self.confirm_was_down = active_keys.confirm;
+        self.update_seed(active_keys);
+    }
+    // Keys held when the menu comes back only count once released
+    pub fn reopen(&mut self, active_keys: &engine::MoveKeys) {
+        self.level_start = false;
+        self.bindings_request = false;
+        self.left_was_down = active_keys.left;
+        self.right_was_down = active_keys.right;
+        self.confirm_was_down = active_keys.confirm;
     }
     pub fn request_level_start(&self) -> bool {
         self.level_start
     }
+    pub fn request_bindings(&self) -> bool {
+        self.bindings_request
+    }
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+    pub fn has_course(&self) -> bool {
+        self.course.is_some()
+    }
+
+    fn update_seed(&mut self, active_keys: &engine::MoveKeys) {
+        if self.course.is_some() {
+            return;
+        }
+        if active_keys.left && !self.left_was_down {
+            self.seed = self.seed.wrapping_sub(1);
+        }
+        if active_keys.right && !self.right_was_down {
+            self.seed = self.seed.wrapping_add(1);
+        }
+        self.left_was_down = active_keys.left;
+        self.right_was_down = active_keys.right;
+    }
+
+    fn draw_seed(&self, bindings: &bindings::Bindings) {
+        let message = match &self.course {
+            Some(course) => format! {"course: {}", course},
+            None => format! {
+                "[{}] < seed: {} > [{}]",
+                bindings.get_label(bindings::Action::Left),
+                self.seed,
+                bindings.get_label(bindings::Action::Right)
+            },
+        };
+        engine::draw_text(&message, SEED_ANCHOR, engine::TEXT_DEFAULT);
+    }
+
+    fn draw_instructions(&self, bindings: &bindings::Bindings) {
+        for (index, (action, description)) in INSTRUCTIONS.iter().enumerate() {
+            let anchor = engine::PointScreen {
+                x: INSTRUCTIONS_X,
+                y: INSTRUCTIONS_Y1 + index as f32 * INSTRUCTIONS_Y_STEP,
+            };
+            let message = format! {"[{}]: {}", bindings.get_label(*action), description};
+            engine::draw_text(&message, anchor, engine::TEXT_INSTRUCTIONS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_PRESS: engine::MoveKeys = engine::MoveKeys::NONE;
+
+    const LEFT_PRESS: engine::MoveKeys = engine::MoveKeys {
+        left: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const RIGHT_PRESS: engine::MoveKeys = engine::MoveKeys {
+        right: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    const CONFIRM_PRESS: engine::MoveKeys = engine::MoveKeys {
+        confirm: true,
+        ..engine::MoveKeys::NONE
+    };
+
+    #[test]
+    fn right_press_increments_seed() {
+        let mut menu = StartMenu::new(10);
+        menu.update(&RIGHT_PRESS);
+        assert_eq! {menu.get_seed(), 11}
+    }
+
+    #[test]
+    fn left_press_decrements_seed() {
+        let mut menu = StartMenu::new(10);
+        menu.update(&LEFT_PRESS);
+        assert_eq! {menu.get_seed(), 9}
+    }
+
+    #[test]
+    fn held_key_changes_seed_once() {
+        let mut menu = StartMenu::new(10);
+        menu.update(&RIGHT_PRESS);
+        menu.update(&RIGHT_PRESS);
+        menu.update(&NO_PRESS);
+        menu.update(&RIGHT_PRESS);
+        assert_eq! {menu.get_seed(), 12}
+    }
+
+    #[test]
+    fn course_seed_is_fixed() {
+        let mut menu = StartMenu::with_course("slalom", 10);
+        menu.update(&RIGHT_PRESS);
+        menu.update(&NO_PRESS);
+        menu.update(&LEFT_PRESS);
+        assert_eq! {menu.get_seed(), 10}
+    }
+
+    #[test]
+    fn confirm_requests_bindings() {
+        let mut menu = StartMenu::new(10);
+        menu.update(&CONFIRM_PRESS);
+        assert! {menu.request_bindings()}
+        menu.reopen(&CONFIRM_PRESS);
+        menu.update(&CONFIRM_PRESS);
+        assert! {!menu.request_bindings()}
+    }
 
-    fn draw_instructions(&self) {
-        engine::draw_text(
-            " [W] : Accelerate",
-            INSTRUCTIONS_ANCHOR,
-            engine::TEXT_INSTRUCTIONS,
-        );
-        engine::draw_text(
-            "[A|D]: Left|Right",
-            INSTRUCTIONS_ANCHOR2,
-            engine::TEXT_INSTRUCTIONS,
-        );
-        engine::draw_text(
-            " [S] : Decelerate",
-            INSTRUCTIONS_ANCHOR3,
-            engine::TEXT_INSTRUCTIONS,
-        );
+    #[test]
+    fn tap_starts_level() {
+        let mut menu = StartMenu::new(10);
+        menu.update(&engine::MoveKeys {
+            touch: true,
+            ..engine::MoveKeys::NONE
+        });
+        assert! {menu.request_level_start()}
+        assert_eq! {menu.get_seed(), 10}
     }
 }
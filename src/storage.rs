@@ -0,0 +1,98 @@
+// Small key-value store for data that outlives a session: files natively,
+// local storage in the browser, and memory in tests.
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+const SAVE_DIRECTORY: &str = "saves";
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(file_path(key)).ok()
+}
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+pub fn save(key: &str, value: &str) {
+    let result =
+        std::fs::create_dir_all(SAVE_DIRECTORY).and_then(|_| std::fs::write(file_path(key), value));
+    if let Err(error) = result {
+        eprintln!("could not save {}: {}", key, error);
+    }
+}
+
+#[cfg(all(not(test), not(target_arch = "wasm32")))]
+fn file_path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new(SAVE_DIRECTORY).join(format!("{}.txt", key))
+}
+
+// Implemented by the storage plugin in index.html
+#[cfg(all(not(test), target_arch = "wasm32"))]
+extern "C" {
+    fn phoenix_storage_length(key: *const u8, key_length: u32) -> i32;
+    fn phoenix_storage_get(key: *const u8, key_length: u32, value: *mut u8, value_length: u32);
+    fn phoenix_storage_set(key: *const u8, key_length: u32, value: *const u8, value_length: u32);
+}
+
+#[cfg(all(not(test), target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    let length = unsafe { phoenix_storage_length(key.as_ptr(), key.len() as u32) };
+    if length < 0 {
+        return None;
+    }
+    let mut value = vec![0u8; length as usize];
+    unsafe {
+        phoenix_storage_get(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_mut_ptr(),
+            value.len() as u32,
+        )
+    };
+    String::from_utf8(value).ok()
+}
+
+#[cfg(all(not(test), target_arch = "wasm32"))]
+pub fn save(key: &str, value: &str) {
+    unsafe {
+        phoenix_storage_set(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        )
+    };
+}
+
+#[cfg(test)]
+thread_local! {
+    static MEMORY: std::cell::RefCell<std::collections::HashMap<String, String>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}
+
+#[cfg(test)]
+pub fn load(key: &str) -> Option<String> {
+    MEMORY.with(|memory| memory.borrow().get(key).cloned())
+}
+
+#[cfg(test)]
+pub fn save(key: &str, value: &str) {
+    MEMORY.with(|memory| {
+        memory
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_value_is_loaded() {
+        save("key", "value");
+        assert_eq! {load("key"), Some("value".to_string())}
+    }
+
+    #[test]
+    fn missing_key_loads_nothing() {
+        assert_eq! {load("missing"), None}
+    }
+}
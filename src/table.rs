@@ -0,0 +1,144 @@
+// Key-value tables of the small TOML subset shared by map and tuning files
+
+#[derive(Debug)]
+pub enum TableError {
+    Syntax { line: usize, message: String },
+    MissingKey { line: usize, key: &'static str },
+}
+
+pub fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(start) => &line[..start],
+        None => line,
+    }
+}
+
+struct Entry {
+    line: usize,
+    key: String,
+    value: String,
+}
+
+pub struct Table {
+    line: usize,
+    entries: Vec<Entry>,
+}
+
+impl Table {
+    pub fn new(line: usize) -> Table {
+        Table {
+            line,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn insert(&mut self, line: usize, text: &str) -> Result<(), TableError> {
+        let (key, value) = text.split_once('=').ok_or(TableError::Syntax {
+            line,
+            message: format!("expected 'key = value', found '{}'", text),
+        })?;
+        let key = key.trim();
+        if self.find(key).is_some() {
+            return Err(TableError::Syntax {
+                line,
+                message: format!("duplicate key '{}'", key),
+            });
+        }
+        self.entries.push(Entry {
+            line,
+            key: key.to_string(),
+            value: value.trim().to_string(),
+        });
+        Ok(())
+    }
+
+    fn find(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.key == key)
+    }
+
+    pub fn get_or<T: std::str::FromStr>(
+        &self,
+        key: &'static str,
+        default: T,
+    ) -> Result<T, TableError> {
+        match self.find(key) {
+            Some(entry) => parse_scalar(entry),
+            None => Ok(default),
+        }
+    }
+
+    pub fn get_f32(&self, key: &'static str) -> Result<f32, TableError> {
+        parse_scalar(self.get_entry(key)?)
+    }
+
+    pub fn get_pair(&self, key: &'static str) -> Result<(f32, f32), TableError> {
+        let entry = self.get_entry(key)?;
+        let bad_pair = || TableError::Syntax {
+            line: entry.line,
+            message: format!("'{}' must be a pair like [x, z]", entry.key),
+        };
+        let inner = entry
+            .value
+            .strip_prefix('[')
+            .and_then(|value| value.strip_suffix(']'))
+            .ok_or_else(bad_pair)?;
+        let (first, second) = inner.split_once(',').ok_or_else(bad_pair)?;
+        let first = first.trim().parse::<f32>().map_err(|_| bad_pair())?;
+        let second = second.trim().parse::<f32>().map_err(|_| bad_pair())?;
+        Ok((first, second))
+    }
+
+    fn get_entry(&self, key: &'static str) -> Result<&Entry, TableError> {
+        self.find(key).ok_or(TableError::MissingKey {
+            line: self.line,
+            key,
+        })
+    }
+
+    // Reported on the key's line, or the table's when the key kept its default
+    pub fn require(
+        &self,
+        key: &'static str,
+        condition: bool,
+        requirement: &str,
+    ) -> Result<(), TableError> {
+        if condition {
+            return Ok(());
+        }
+        let line = self.find(key).map_or(self.line, |entry| entry.line);
+        Err(TableError::Syntax {
+            line,
+            message: format!("'{}' must be {}", key, requirement),
+        })
+    }
+
+    // Also rules out nan and infinities, which the comparisons would let through
+    pub fn require_positive(&self, key: &'static str, value: f32) -> Result<(), TableError> {
+        self.require(key, value.is_finite() && value > 0.0, "a positive number")
+    }
+
+    pub fn require_non_negative(&self, key: &'static str, value: f32) -> Result<(), TableError> {
+        self.require(key, value.is_finite() && value >= 0.0, "zero or more")
+    }
+
+    pub fn check_all_used(&self, known_keys: &[&str]) -> Result<(), TableError> {
+        match self
+            .entries
+            .iter()
+            .find(|entry| !known_keys.contains(&entry.key.as_str()))
+        {
+            Some(entry) => Err(TableError::Syntax {
+                line: entry.line,
+                message: format!("unknown key '{}'", entry.key),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse_scalar<T: std::str::FromStr>(entry: &Entry) -> Result<T, TableError> {
+    entry.value.parse::<T>().map_err(|_| TableError::Syntax {
+        line: entry.line,
+        message: format!("invalid value '{}' for '{}'", entry.value, entry.key),
+    })
+}
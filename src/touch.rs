@@ -0,0 +1,282 @@
+use super::engine;
+
+// Vertical drag for full throttle or full brake
+const SWIPE_RANGE: f32 = 120.0;
+const BUTTON_SIZE: f32 = 50.0;
+const BUTTON_MARGIN: f32 = 20.0;
+const ICON_INSET: f32 = 0.35;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct TrackedTouch {
+    id: u64,
+    start: engine::PointScreen,
+    position: engine::PointScreen,
+}
+
+// A finger steers towards the half of the screen it is on and sets the throttle by
+// dragging up or down from where it first landed. The corner buttons pause and hop.
+pub struct TouchControls {
+    touches: Vec<TrackedTouch>,
+    // Nothing is drawn until the screen is first touched, so desktops keep a clean view
+    shown: bool,
+}
+
+impl TouchControls {
+    pub fn new() -> TouchControls {
+        TouchControls {
+            touches: Vec::new(),
+            shown: false,
+        }
+    }
+
+    pub fn update(&mut self, touches: &[engine::Touch]) -> engine::MoveKeys {
+        self.touches
+            .retain(|tracked| touches.iter().any(|touch| touch.id == tracked.id));
+        for touch in touches {
+            match self
+                .touches
+                .iter_mut()
+                .find(|tracked| tracked.id == touch.id)
+            {
+                Some(tracked) => tracked.position = touch.position,
+                None => self.touches.push(TrackedTouch {
+                    id: touch.id,
+                    start: touch.position,
+                    position: touch.position,
+                }),
+            }
+        }
+        if !touches.is_empty() {
+            self.shown = true;
+        }
+        self.touches
+            .iter()
+            .map(touch_to_keys)
+            .fold(engine::MoveKeys::NONE, |keys, touch_keys| {
+                keys.merge(&touch_keys)
+            })
+    }
+
+    pub fn draw(&self) {
+        if !self.shown {
+            return;
+        }
+        let half_height = 0.50 * engine::get_screen_height();
+        engine::draw_line_personalized(
+            engine::PointScreen {
+                x: 0.0,
+                y: -half_height,
+            },
+            engine::PointScreen {
+                x: 0.0,
+                y: half_height,
+            },
+            engine::GRID_LINE,
+        );
+        draw_pause_button();
+        draw_jump_button();
+        for touch in self.touches.iter().filter(|touch| !on_button(touch.start)) {
+            engine::draw_line_personalized(touch.start, touch.position, engine::HUD_LINE);
+        }
+    }
+}
+
+fn touch_to_keys(touch: &TrackedTouch) -> engine::MoveKeys {
+    if on_pause_button(touch.start) {
+        return engine::MoveKeys {
+            pause: true,
+            touch: true,
+            ..engine::MoveKeys::NONE
+        };
+    }
+    if on_jump_button(touch.start) {
+        return engine::MoveKeys {
+            hop: true,
+            touch: true,
+            ..engine::MoveKeys::NONE
+        };
+    }
+    let steer = if touch.position.x < 0.0 { -1.0 } else { 1.0 };
+    let drag = (touch.position.y - touch.start.y) / SWIPE_RANGE;
+    engine::MoveKeys {
+        touch: true,
+        steer_axis: steer,
+        throttle_axis: engine::quantize_axis(drag),
+        ..engine::MoveKeys::NONE
+    }
+}
+
+// Right hand corners, top or bottom, as (min, max) corners
+fn button_bounds(top: bool) -> (engine::PointScreen, engine::PointScreen) {
+    let half_height = 0.50 * engine::get_screen_height();
+    let max_x = 0.50 * engine::get_screen_width() - BUTTON_MARGIN;
+    let min_y = if top {
+        half_height - BUTTON_MARGIN - BUTTON_SIZE
+    } else {
+        -half_height + BUTTON_MARGIN
+    };
+    let min = engine::PointScreen {
+        x: max_x - BUTTON_SIZE,
+        y: min_y,
+    };
+    let max = engine::PointScreen {
+        x: max_x,
+        y: min_y + BUTTON_SIZE,
+    };
+    (min, max)
+}
+
+fn in_bounds(
+    point: engine::PointScreen,
+    bounds: (engine::PointScreen, engine::PointScreen),
+) -> bool {
+    let (min, max) = bounds;
+    (min.x..=max.x).contains(&point.x) && (min.y..=max.y).contains(&point.y)
+}
+
+fn on_pause_button(point: engine::PointScreen) -> bool {
+    in_bounds(point, button_bounds(true))
+}
+
+fn on_jump_button(point: engine::PointScreen) -> bool {
+    in_bounds(point, button_bounds(false))
+}
+
+fn on_button(point: engine::PointScreen) -> bool {
+    on_pause_button(point) || on_jump_button(point)
+}
+
+fn draw_button_box(bounds: (engine::PointScreen, engine::PointScreen)) {
+    let (min, max) = bounds;
+    let corners = [
+        min,
+        engine::PointScreen { x: max.x, y: min.y },
+        max,
+        engine::PointScreen { x: min.x, y: max.y },
+    ];
+    for index in 0..corners.len() {
+        let next = corners[(index + 1) % corners.len()];
+        engine::draw_line_personalized(corners[index], next, engine::HUD_LINE);
+    }
+}
+
+fn draw_pause_button() {
+    let bounds = button_bounds(true);
+    draw_button_box(bounds);
+    let (min, max) = bounds;
+    let inset = ICON_INSET * BUTTON_SIZE;
+    for x in [min.x + inset, max.x - inset] {
+        engine::draw_line_personalized(
+            engine::PointScreen {
+                x,
+                y: min.y + inset,
+            },
+            engine::PointScreen {
+                x,
+                y: max.y - inset,
+            },
+            engine::HUD_LINE,
+        );
+    }
+}
+
+// An upward chevron
+fn draw_jump_button() {
+    let bounds = button_bounds(false);
+    draw_button_box(bounds);
+    let (min, max) = bounds;
+    let inset = ICON_INSET * BUTTON_SIZE;
+    let tip = engine::PointScreen {
+        x: 0.50 * (min.x + max.x),
+        y: max.y - inset,
+    };
+    for x in [min.x + inset, max.x - inset] {
+        engine::draw_line_personalized(
+            engine::PointScreen {
+                x,
+                y: min.y + inset,
+            },
+            tip,
+            engine::HUD_LINE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recording;
+
+    fn touch_at(id: u64, x: f32, y: f32) -> engine::Touch {
+        engine::Touch {
+            id,
+            position: engine::PointScreen { x, y },
+        }
+    }
+
+    fn draw_controls(
+        controls: &TouchControls,
+    ) -> std::rc::Rc<std::cell::RefCell<recording::RecordedFrame>> {
+        let frame = recording::install_recorder();
+        controls.draw();
+        frame
+    }
+
+    #[test]
+    fn screen_halves_steer() {
+        let mut controls = TouchControls::new();
+        let keys = controls.update(&[touch_at(1, -200.0, -100.0)]);
+        assert_eq! {keys.get_steer(), -1.0}
+        assert! {keys.touch && !keys.left}
+        let keys = controls.update(&[touch_at(2, 150.0, -100.0)]);
+        assert_eq! {keys.get_steer(), 1.0}
+    }
+
+    #[test]
+    fn drag_sets_throttle() {
+        let mut controls = TouchControls::new();
+        controls.update(&[touch_at(1, 200.0, -100.0)]);
+        let keys = controls.update(&[touch_at(1, 200.0, -100.0 + 0.50 * SWIPE_RANGE)]);
+        assert! {(keys.get_throttle() - 0.50).abs() < 1.0 / engine::AXIS_STEPS}
+        let keys = controls.update(&[touch_at(1, 200.0, -100.0 - 3.0 * SWIPE_RANGE)]);
+        assert_eq! {keys.get_throttle(), -1.0}
+    }
+
+    #[test]
+    fn lifted_finger_starts_a_new_drag() {
+        let mut controls = TouchControls::new();
+        controls.update(&[touch_at(1, 200.0, -100.0)]);
+        controls.update(&[touch_at(1, 200.0, 0.0)]);
+        assert_eq! {controls.update(&[]), engine::MoveKeys::NONE}
+        let keys = controls.update(&[touch_at(1, 200.0, 0.0)]);
+        assert_eq! {keys.get_throttle(), 0.0}
+    }
+
+    #[test]
+    fn corner_button_pauses() {
+        let mut controls = TouchControls::new();
+        let keys = controls.update(&[touch_at(1, 355.0, 255.0)]);
+        assert! {keys.pause}
+        assert_eq! {keys.get_steer(), 0.0}
+    }
+
+    #[test]
+    fn bottom_corner_button_hops() {
+        let mut controls = TouchControls::new();
+        let keys = controls.update(&[touch_at(1, 355.0, -255.0)]);
+        assert! {keys.hop && !keys.confirm && !keys.pause}
+        assert_eq! {keys.get_steer(), 0.0}
+    }
+
+    #[test]
+    fn drawn_only_after_a_touch() {
+        let mut controls = TouchControls::new();
+        assert! {draw_controls(&controls).borrow().lines.is_empty()}
+        controls.update(&[touch_at(1, 200.0, -100.0)]);
+        controls.update(&[touch_at(1, 200.0, -50.0)]);
+        let frame = draw_controls(&controls);
+        let frame = frame.borrow();
+        // Divider, pause and jump button boxes and icons, and the drag
+        assert_eq! {frame.lines.len(), 1 + 4 + 2 + 4 + 2 + 1}
+    }
+}
@@ -0,0 +1,511 @@
+use super::table;
+
+// Read natively when present, so values can be tweaked without a rebuild
+#[cfg(not(target_arch = "wasm32"))]
+pub const TUNING_FILE: &str = "tuning.toml";
+const DEFAULT_TUNING: &str = include_str!("../tuning.toml");
+const PLAYER_TABLE: &str = "[player]";
+const MAP_TABLE: &str = "[map]";
+const CAMERA_TABLE: &str = "[camera]";
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PlayerParams {
+    pub initial_speed: f32,
+    pub acceleration: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub tilt_angle_deg: f32,
+    pub float_height: f32,
+    pub roll_stiffness: f32,
+    pub roll_damping: f32,
+    pub lateral_acceleration: f32,
+    pub lateral_drag: f32,
+    pub max_lateral_speed: f32,
+    // Vertical speed of a hop, gravity brings the ship back to its float height
+    pub jump_speed: f32,
+    pub gravity: f32,
+}
+
+impl PlayerParams {
+    pub const DEFAULT: PlayerParams = PlayerParams {
+        initial_speed: 200.0,
+        acceleration: 1000.0,
+        min_speed: 200.0,
+        max_speed: 2000.0,
+        tilt_angle_deg: 45.0,
+        float_height: 15.0 + 0.50 * super::PLAYER_WIDTH * 0.70,
+        roll_stiffness: 200.0,
+        roll_damping: 25.0,
+        lateral_acceleration: 4000.0,
+        lateral_drag: 4.0,
+        max_lateral_speed: 900.0,
+        jump_speed: 800.0,
+        gravity: 2000.0,
+    };
+
+    pub fn tilt_angle_rad(&self) -> f32 {
+        self.tilt_angle_deg.to_radians()
+    }
+
+    pub fn to_text(self) -> String {
+        fields_to_text(PLAYER_TABLE, self, &PLAYER_FIELDS)
+    }
+
+    fn from_table(table: &table::Table) -> Result<PlayerParams, table::TableError> {
+        let params = fields_from_table(table, PlayerParams::DEFAULT, &PLAYER_FIELDS)?;
+        table.require(
+            "max_speed",
+            params.max_speed >= params.min_speed,
+            "at least min_speed",
+        )?;
+        // Banking divides by the sine of the full tilt
+        table.require(
+            "tilt_angle_deg",
+            params.tilt_angle_deg <= 90.0,
+            "at most 90",
+        )?;
+        Ok(params)
+    }
+}
+
+// Zero damping leaves the roll an undamped spring, zero drag lets the ship slide on
+const PLAYER_FIELDS: [Field<PlayerParams>; 13] = [
+    (
+        "initial_speed",
+        |params| &mut params.initial_speed,
+        POSITIVE,
+    ),
+    ("acceleration", |params| &mut params.acceleration, POSITIVE),
+    ("min_speed", |params| &mut params.min_speed, POSITIVE),
+    ("max_speed", |params| &mut params.max_speed, POSITIVE),
+    (
+        "tilt_angle_deg",
+        |params| &mut params.tilt_angle_deg,
+        POSITIVE,
+    ),
+    ("float_height", |params| &mut params.float_height, POSITIVE),
+    (
+        "roll_stiffness",
+        |params| &mut params.roll_stiffness,
+        POSITIVE,
+    ),
+    (
+        "roll_damping",
+        |params| &mut params.roll_damping,
+        NON_NEGATIVE,
+    ),
+    (
+        "lateral_acceleration",
+        |params| &mut params.lateral_acceleration,
+        POSITIVE,
+    ),
+    (
+        "lateral_drag",
+        |params| &mut params.lateral_drag,
+        NON_NEGATIVE,
+    ),
+    (
+        "max_lateral_speed",
+        |params| &mut params.max_lateral_speed,
+        POSITIVE,
+    ),
+    ("jump_speed", |params| &mut params.jump_speed, POSITIVE),
+    ("gravity", |params| &mut params.gravity, POSITIVE),
+];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MapParams {
+    pub tile_size: f32,
+    pub fence_width: f32,
+    pub fence_height: f32,
+    pub obstacle_side_min: f32,
+    pub obstacle_side_max: f32,
+    // Share of the map area covered by generated obstacles, before overlaps are dropped
+    pub obstacle_density: f32,
+}
+
+impl MapParams {
+    pub const DEFAULT: MapParams = MapParams {
+        tile_size: 100.0,
+        fence_width: 40.0,
+        fence_height: 100.0,
+        obstacle_side_min: 50.0,
+        obstacle_side_max: 150.0,
+        obstacle_density: 0.10,
+    };
+
+    pub fn average_obstacle_area(&self) -> f32 {
+        let side = 0.50 * (self.obstacle_side_min + self.obstacle_side_max);
+        side * side
+    }
+
+    pub fn to_text(self) -> String {
+        fields_to_text(MAP_TABLE, self, &MAP_FIELDS)
+    }
+
+    fn from_table(table: &table::Table) -> Result<MapParams, table::TableError> {
+        let params = fields_from_table(table, MapParams::DEFAULT, &MAP_FIELDS)?;
+        table.require(
+            "obstacle_side_max",
+            params.obstacle_side_max >= params.obstacle_side_min,
+            "at least obstacle_side_min",
+        )?;
+        Ok(params)
+    }
+}
+
+const MAP_FIELDS: [Field<MapParams>; 6] = [
+    ("tile_size", |params| &mut params.tile_size, POSITIVE),
+    ("fence_width", |params| &mut params.fence_width, POSITIVE),
+    ("fence_height", |params| &mut params.fence_height, POSITIVE),
+    (
+        "obstacle_side_min",
+        |params| &mut params.obstacle_side_min,
+        POSITIVE,
+    ),
+    (
+        "obstacle_side_max",
+        |params| &mut params.obstacle_side_max,
+        POSITIVE,
+    ),
+    (
+        "obstacle_density",
+        |params| &mut params.obstacle_density,
+        SHARE,
+    ),
+];
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CameraParams {
+    // How much of the ship's roll the horizon follows, 0 keeps it level and 1 banks with the cockpit
+    pub roll_share: f32,
+}
+
+impl CameraParams {
+    pub const DEFAULT: CameraParams = CameraParams { roll_share: 0.0 };
+
+    pub fn to_text(self) -> String {
+        fields_to_text(CAMERA_TABLE, self, &CAMERA_FIELDS)
+    }
+
+    fn from_table(table: &table::Table) -> Result<CameraParams, table::TableError> {
+        fields_from_table(table, CameraParams::DEFAULT, &CAMERA_FIELDS)
+    }
+}
+
+const CAMERA_FIELDS: [Field<CameraParams>; 1] =
+    [("roll_share", |params| &mut params.roll_share, SHARE)];
+
+// Each key with the field it sets and the check its value has to pass
+type Field<P> = (&'static str, fn(&mut P) -> &mut f32, Check);
+type Check = fn(&table::Table, &'static str, f32) -> Result<(), table::TableError>;
+
+const POSITIVE: Check = table::Table::require_positive;
+const NON_NEGATIVE: Check = table::Table::require_non_negative;
+const SHARE: Check = require_share;
+
+fn fields_to_text<P>(table_name: &str, mut params: P, fields: &[Field<P>]) -> String {
+    let mut text = String::from(table_name) + "\n";
+    for (key, field, _) in fields {
+        text += &format!("{} = {}\n", key, field(&mut params));
+    }
+    text
+}
+
+// Missing keys keep their value in `default`
+fn fields_from_table<P>(
+    table: &table::Table,
+    default: P,
+    fields: &[Field<P>],
+) -> Result<P, table::TableError> {
+    let mut params = default;
+    for (key, field, _) in fields {
+        let value = field(&mut params);
+        *value = table.get_or(key, *value)?;
+    }
+    let keys: Vec<&str> = fields.iter().map(|(key, _, _)| *key).collect();
+    table.check_all_used(&keys)?;
+    for (key, field, check) in fields {
+        check(table, key, *field(&mut params))?;
+    }
+    Ok(params)
+}
+
+fn require_share(
+    table: &table::Table,
+    key: &'static str,
+    value: f32,
+) -> Result<(), table::TableError> {
+    table.require(key, (0.0..=1.0).contains(&value), "between 0 and 1")
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    // The web build only reads the embedded file
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Io(std::io::Error),
+    Syntax {
+        line: usize,
+        message: String,
+    },
+}
+
+impl From<table::TableError> for TuningError {
+    fn from(error: table::TableError) -> TuningError {
+        match error {
+            table::TableError::Syntax { line, message } => TuningError::Syntax { line, message },
+            // Every tuning key has a default
+            table::TableError::MissingKey { line, key } => TuningError::Syntax {
+                line,
+                message: format!("missing key '{}'", key),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for TuningError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TuningError::Io(error) => write!(f, "{}", error),
+            TuningError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Tuning {
+    pub player: PlayerParams,
+    pub map: MapParams,
+    pub camera: CameraParams,
+}
+
+impl Tuning {
+    pub const DEFAULT: Tuning = Tuning {
+        player: PlayerParams::DEFAULT,
+        map: MapParams::DEFAULT,
+        camera: CameraParams::DEFAULT,
+    };
+
+    pub fn to_text(self) -> String {
+        [
+            self.player.to_text(),
+            self.map.to_text(),
+            self.camera.to_text(),
+        ]
+        .join("\n")
+    }
+
+    // Missing keys keep their default, unknown keys and tables are errors
+    pub fn parse(text: &str) -> Result<Tuning, TuningError> {
+        let mut player = table::Table::new(1);
+        let mut map = table::Table::new(1);
+        let mut camera = table::Table::new(1);
+        let mut current: Option<&mut table::Table> = None;
+        for (index, raw_line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = table::strip_comment(raw_line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line == PLAYER_TABLE {
+                current = Some(&mut player);
+                continue;
+            }
+            if line == MAP_TABLE {
+                current = Some(&mut map);
+                continue;
+            }
+            if line == CAMERA_TABLE {
+                current = Some(&mut camera);
+                continue;
+            }
+            if line.starts_with('[') {
+                return Err(TuningError::Syntax {
+                    line: line_number,
+                    message: format!("unknown table '{}'", line),
+                });
+            }
+            match current.as_mut() {
+                Some(table) => table.insert(line_number, line)?,
+                None => {
+                    return Err(TuningError::Syntax {
+                        line: line_number,
+                        message: format!(
+                            "expected {}, {} or {} first",
+                            PLAYER_TABLE, MAP_TABLE, CAMERA_TABLE
+                        ),
+                    })
+                }
+            }
+        }
+        Ok(Tuning {
+            player: PlayerParams::from_table(&player)?,
+            map: MapParams::from_table(&map)?,
+            camera: CameraParams::from_table(&camera)?,
+        })
+    }
+
+    // Falls back to the values built into the binary if the file is missing or broken
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Tuning {
+        if !std::path::Path::new(TUNING_FILE).exists() {
+            return Tuning::embedded();
+        }
+        Tuning::read_file().unwrap_or_else(|error| {
+            eprintln!("could not read {}: {}", TUNING_FILE, error);
+            Tuning::embedded()
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_file() -> Result<Tuning, TuningError> {
+        let text = std::fs::read_to_string(TUNING_FILE).map_err(TuningError::Io)?;
+        Tuning::parse(&text)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Tuning {
+        Tuning::embedded()
+    }
+
+    fn embedded() -> Tuning {
+        Tuning::parse(DEFAULT_TUNING).expect("built-in tuning is valid")
+    }
+}
+
+// Debug builds pick up edits to the tuning file while the game runs
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub struct TuningWatcher {
+    modified: Option<std::time::SystemTime>,
+}
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+impl TuningWatcher {
+    pub fn new() -> TuningWatcher {
+        TuningWatcher {
+            modified: modified_time(),
+        }
+    }
+
+    pub fn poll(&mut self) -> Option<Tuning> {
+        let modified = modified_time();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        match Tuning::read_file() {
+            Ok(tuning) => {
+                eprintln!("reloaded {}", TUNING_FILE);
+                Some(tuning)
+            }
+            // An invalid edit keeps the tuning already in play
+            Err(error) => {
+                eprintln!("could not reload {}: {}", TUNING_FILE, error);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+fn modified_time() -> Option<std::time::SystemTime> {
+    std::fs::metadata(TUNING_FILE)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(not(all(debug_assertions, not(target_arch = "wasm32"))))]
+pub struct TuningWatcher;
+
+#[cfg(not(all(debug_assertions, not(target_arch = "wasm32"))))]
+impl TuningWatcher {
+    pub fn new() -> TuningWatcher {
+        TuningWatcher
+    }
+
+    pub fn poll(&mut self) -> Option<Tuning> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_file_matches_defaults() {
+        assert_eq! {Tuning::parse(DEFAULT_TUNING).unwrap(), Tuning::DEFAULT}
+    }
+
+    #[test]
+    fn missing_keys_keep_defaults() {
+        let tuning = Tuning::parse("[player]\nmax_speed = 3000 # faster\n\n[map]\n").unwrap();
+        assert_eq! {tuning.player.max_speed, 3000.0}
+        assert_eq! {tuning.player.min_speed, PlayerParams::DEFAULT.min_speed}
+        assert_eq! {tuning.map, MapParams::DEFAULT}
+        assert_eq! {tuning.camera, CameraParams::DEFAULT}
+        assert_eq! {Tuning::parse("").unwrap(), Tuning::DEFAULT}
+    }
+
+    #[test]
+    fn unknown_keys_and_tables_are_errors() {
+        let error = Tuning::parse("[player]\nmax_sped = 3000\n").unwrap_err();
+        assert! {matches!(error, TuningError::Syntax { line: 2, .. })}
+        let error = Tuning::parse("[sound]\n").unwrap_err();
+        assert! {matches!(error, TuningError::Syntax { line: 1, .. })}
+        let error = Tuning::parse("max_speed = 3000\n").unwrap_err();
+        assert! {matches!(error, TuningError::Syntax { line: 1, .. })}
+        let error = Tuning::parse("[map]\ntile_size = wide\n").unwrap_err();
+        assert! {matches!(error, TuningError::Syntax { line: 2, .. })}
+    }
+
+    fn syntax_error_line(text: &str) -> Option<usize> {
+        match Tuning::parse(text) {
+            Err(TuningError::Syntax { line, .. }) => Some(line),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn values_that_break_the_game_are_errors() {
+        assert_eq! {syntax_error_line("[player]\nmin_speed = 100\nmax_speed = 50\n"), Some(3)}
+        assert_eq! {syntax_error_line("[player]\nmin_speed = 3000\n"), Some(1)}
+        assert_eq! {syntax_error_line("[player]\nmax_lateral_speed = -900\n"), Some(2)}
+        assert_eq! {syntax_error_line("[player]\ngravity = nan\n"), Some(2)}
+        assert_eq! {syntax_error_line("[player]\ntilt_angle_deg = 0\n"), Some(2)}
+        assert_eq! {syntax_error_line("[map]\ntile_size = 0\n"), Some(2)}
+        assert_eq! {syntax_error_line("[map]\n\nfence_width = -40\n"), Some(3)}
+        assert_eq! {syntax_error_line("[map]\nobstacle_side_min = 200\n"), Some(1)}
+        assert_eq! {
+            syntax_error_line("[map]\nobstacle_side_min = 20\nobstacle_side_max = 10\n"),
+            Some(3)
+        }
+        assert_eq! {syntax_error_line("[map]\nobstacle_density = inf\n"), Some(2)}
+        assert_eq! {syntax_error_line("[camera]\nroll_share = 2\n"), Some(2)}
+    }
+
+    #[test]
+    fn zero_drag_and_damping_are_allowed() {
+        let tuning = Tuning::parse("[player]\nlateral_drag = 0\nroll_damping = 0\n").unwrap();
+        assert_eq! {tuning.player.lateral_drag, 0.0}
+        assert_eq! {tuning.player.roll_damping, 0.0}
+        assert_eq! {syntax_error_line("[player]\nlateral_drag = -1\n"), Some(2)}
+    }
+
+    #[test]
+    fn tuning_round_trips_through_text() {
+        let tuning = Tuning {
+            player: PlayerParams {
+                lateral_drag: 2.5,
+                ..PlayerParams::DEFAULT
+            },
+            map: MapParams {
+                tile_size: 80.0,
+                obstacle_density: 0.125,
+                ..MapParams::DEFAULT
+            },
+            camera: CameraParams { roll_share: 0.5 },
+        };
+        assert_eq! {Tuning::parse(&tuning.to_text()).unwrap(), tuning}
+    }
+}